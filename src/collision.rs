@@ -0,0 +1,245 @@
+use std::cmp::Ordering;
+
+use super::r3::{dot, R3};
+use super::GameObject;
+
+/// Checks if `p` is strictly inside the box with one corner at `corner` spanning `size`.
+/// Components of `size` may be negative, in which case the box extends the other way.
+#[cfg(test)]
+pub fn contains(corner: &R3, size: &R3, p: &R3) -> bool {
+    let within = |c: f64, s: f64, x: f64| {
+        let (min, max) = if s < 0.0 { (c + s, c) } else { (c, c + s) };
+        x > min && x < max
+    };
+
+    within(corner.x, size.x, p.x) && within(corner.y, size.y, p.y) && within(corner.z, size.z, p.z)
+}
+
+/// An axis aligned box around an object in world space, as its min and max
+/// corners, or `None` if its mesh is empty. The box fits around the mesh's
+/// local box rotated into place, so it can be a bit bigger than the mesh.
+pub fn world_aabb(obj: &GameObject) -> Option<(R3, R3)> {
+    let (min, max) = obj.mesh.aabb()?;
+
+    let mut corners = (0..8).map(|i| {
+        let corner = R3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+        obj.pose.orientation.rotate(&corner) + obj.pose.pos
+    });
+
+    let first = corners.next()?;
+    Some(corners.fold((first, first), |(min, max), v| {
+        (
+            R3::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+            R3::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z)),
+        )
+    }))
+}
+
+/// Checks if two axis aligned boxes given by their min and max corners overlap
+pub fn aabbs_overlap((a_min, a_max): (R3, R3), (b_min, b_max): (R3, R3)) -> bool {
+    a_min.x <= b_max.x
+        && b_min.x <= a_max.x
+        && a_min.y <= b_max.y
+        && b_min.y <= a_max.y
+        && a_min.z <= b_max.z
+        && b_min.z <= a_max.z
+}
+
+/// Checks if the bounding spheres of two objects overlap
+pub fn collide(a: &GameObject, b: &GameObject) -> bool {
+    a.pose.pos.distance(&b.pose.pos) < a.bounding_radius + b.bounding_radius
+}
+
+/// How far boxes may overlap before they are pushed apart, so resting objects
+/// aren't nudged back and forth every step
+pub const PENETRATION_SLOP: f64 = 0.01;
+/// The fraction of the overlap past `PENETRATION_SLOP` removed each time
+pub const CORRECTION_PERCENT: f64 = 0.8;
+
+/// The axis the boxes of two objects overlap least along, pointing from `a`
+/// towards `b`, and how far they overlap along it. `None` if they don't overlap.
+pub fn penetration(a: &GameObject, b: &GameObject) -> Option<(R3, f64)> {
+    let (a_min, a_max) = world_aabb(a)?;
+    let (b_min, b_max) = world_aabb(b)?;
+
+    let axes = [
+        (R3::new(1.0, 0.0, 0.0), a_min.x, a_max.x, b_min.x, b_max.x),
+        (R3::new(0.0, 1.0, 0.0), a_min.y, a_max.y, b_min.y, b_max.y),
+        (R3::new(0.0, 0.0, 1.0), a_min.z, a_max.z, b_min.z, b_max.z),
+    ];
+    let (axis, depth, towards_b) = axes
+        .iter()
+        .map(|&(axis, a_min, a_max, b_min, b_max)| {
+            let depth = a_max.min(b_max) - a_min.max(b_min);
+            // either way will do if the boxes are centered on each other
+            let towards_b = b_min + b_max >= a_min + a_max;
+            (axis, depth, towards_b)
+        })
+        .min_by(|x, y| x.1.partial_cmp(&y.1).unwrap_or(Ordering::Equal))?;
+
+    if depth <= 0.0 {
+        return None;
+    }
+    if !towards_b {
+        Some((-axis, depth))
+    } else {
+        Some((axis, depth))
+    }
+}
+
+/// Bounce two objects of equal mass with overlapping boxes off each other.
+/// The normal is the axis they overlap least along, see `penetration`. With a
+/// restitution of 1 they swap the components of their velocities along it,
+/// with 0 they move together along it. Either way they are pushed apart by
+/// most of the overlap past `PENETRATION_SLOP`.
+pub fn resolve_collision(a: &mut GameObject, b: &mut GameObject, restitution: f64) {
+    let (normal, depth) = match penetration(a, b) {
+        Some(found) => found,
+        None => return,
+    };
+
+    // only bounce if they are moving towards each other
+    let approach_speed = dot(&(b.velocity - a.velocity), &normal);
+    if approach_speed < 0.0 {
+        let impulse = normal * (0.5 * (1.0 + restitution) * approach_speed);
+        a.velocity += impulse;
+        b.velocity -= impulse;
+    }
+
+    let correction = CORRECTION_PERCENT * (depth - PENETRATION_SLOP);
+    if correction > 0.0 {
+        a.pose.pos -= normal * (0.5 * correction);
+        b.pose.pos += normal * (0.5 * correction);
+        a.world_vertices = None;
+        b.world_vertices = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::cuboid;
+
+    fn ball(pos: R3, velocity: R3) -> GameObject {
+        let mut obj = crate::tests::object(cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]), pos);
+        obj.velocity = velocity;
+        obj
+    }
+
+    #[test]
+    fn contains_points_strictly_inside_the_box() {
+        let corner = R3::new(1.0, 2.0, 3.0);
+        let size = R3::new(2.0, 2.0, 2.0);
+        assert!(contains(&corner, &size, &R3::new(2.0, 3.0, 4.0)));
+        // the faces are not part of the box
+        assert!(!contains(&corner, &size, &R3::new(1.0, 3.0, 4.0)));
+        assert!(!contains(&corner, &size, &R3::new(2.0, 3.0, 5.0)));
+        assert!(!contains(&corner, &size, &R3::new(2.0, 5.0, 4.0)));
+        assert!(!contains(&corner, &size, &R3::new(-2.0, 3.0, 4.0)));
+    }
+
+    #[test]
+    fn negative_sizes_extend_the_other_way() {
+        let corner = R3::new(1.0, 2.0, 3.0);
+        let size = R3::new(-2.0, 2.0, -2.0);
+        assert!(contains(&corner, &size, &R3::new(0.0, 3.0, 2.0)));
+        assert!(!contains(&corner, &size, &R3::new(2.0, 3.0, 4.0)));
+        assert!(!contains(&corner, &size, &R3::new(-1.0, 3.0, 2.0)));
+    }
+
+    #[test]
+    fn penetration_is_along_the_shallowest_axis() {
+        let a = ball(R3::zero(), R3::zero());
+        let above = ball(R3::new(0.5, 1.8, 0.0), R3::zero());
+        let (normal, depth) = penetration(&a, &above).unwrap();
+        assert_eq!(normal, R3::new(0.0, 1.0, 0.0));
+        assert!((depth - 0.2).abs() < 1e-12);
+
+        let below = ball(R3::new(0.5, -1.8, 0.0), R3::zero());
+        let (normal, _) = penetration(&a, &below).unwrap();
+        assert_eq!(normal, R3::new(0.0, -1.0, 0.0));
+
+        let apart = ball(R3::new(2.5, 0.0, 0.0), R3::zero());
+        assert_eq!(penetration(&a, &apart), None);
+    }
+
+    #[test]
+    fn head_on_collisions_swap_velocities() {
+        let mut a = ball(R3::new(0.0, 0.0, 0.0), R3::new(3.0, 0.0, 0.0));
+        let mut b = ball(R3::new(1.5, 0.0, 0.0), R3::new(-1.0, 0.0, 0.0));
+        assert!(collide(&a, &b));
+
+        resolve_collision(&mut a, &mut b, 1.0);
+        assert!(a.velocity.approx_eq(&R3::new(-1.0, 0.0, 0.0), 1e-12));
+        assert!(b.velocity.approx_eq(&R3::new(3.0, 0.0, 0.0), 1e-12));
+    }
+
+    #[test]
+    fn inelastic_collisions_move_together_and_separate() {
+        let mut a = ball(R3::new(0.0, 0.0, 0.0), R3::new(3.0, 1.0, 0.0));
+        let mut b = ball(R3::new(1.5, 0.0, 0.0), R3::new(-1.0, 0.0, 0.0));
+
+        resolve_collision(&mut a, &mut b, 0.0);
+        // only the components along the normal change
+        assert!(a.velocity.approx_eq(&R3::new(1.0, 1.0, 0.0), 1e-12));
+        assert!(b.velocity.approx_eq(&R3::new(1.0, 0.0, 0.0), 1e-12));
+        // most of the overlap past the slop is removed
+        let (_, depth) = penetration(&a, &b).unwrap();
+        let expected = PENETRATION_SLOP + (1.0 - CORRECTION_PERCENT) * (0.5 - PENETRATION_SLOP);
+        assert!((depth - expected).abs() < 1e-12);
+        assert!(a.world_vertices.is_none() && b.world_vertices.is_none());
+    }
+
+    #[test]
+    fn separating_objects_keep_their_velocities() {
+        let mut a = ball(R3::new(0.0, 0.0, 0.0), R3::new(-1.0, 0.0, 0.0));
+        let mut b = ball(R3::new(1.5, 0.0, 0.0), R3::new(1.0, 0.0, 0.0));
+
+        resolve_collision(&mut a, &mut b, 1.0);
+        assert_eq!(a.velocity, R3::new(-1.0, 0.0, 0.0));
+        assert_eq!(b.velocity, R3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn coincident_centers_still_separate() {
+        let mut a = ball(R3::new(1.0, 2.0, 3.0), R3::zero());
+        let mut b = ball(R3::new(1.0, 2.0, 3.0), R3::zero());
+
+        resolve_collision(&mut a, &mut b, 1.0);
+        let offset = b.pose.pos - a.pose.pos;
+        let expected = CORRECTION_PERCENT * (2.0 - PENETRATION_SLOP);
+        assert!(offset.approx_eq(&R3::new(expected, 0.0, 0.0), 1e-12));
+        assert_eq!(a.velocity, R3::zero());
+        assert_eq!(b.velocity, R3::zero());
+    }
+
+    #[test]
+    fn resting_contacts_settle_without_jitter() {
+        // overlapping by less than the slop is left alone
+        let mut a = ball(R3::zero(), R3::zero());
+        let mut b = ball(R3::new(2.0 - 0.5 * PENETRATION_SLOP, 0.0, 0.0), R3::zero());
+        let (a_start, b_start) = (a.pose.pos, b.pose.pos);
+        for _ in 0..100 {
+            resolve_collision(&mut a, &mut b, 1.0);
+        }
+        assert_eq!((a.pose.pos, b.pose.pos), (a_start, b_start));
+        assert_eq!((a.velocity, b.velocity), (R3::zero(), R3::zero()));
+
+        // a deeper overlap settles at the slop instead of bouncing around it
+        let mut b = ball(R3::new(1.5, 0.0, 0.0), R3::zero());
+        let mut last = b.pose.pos;
+        for _ in 0..50 {
+            resolve_collision(&mut a, &mut b, 1.0);
+            let (_, depth) = penetration(&a, &b).unwrap();
+            assert!(depth >= PENETRATION_SLOP);
+            assert!(b.pose.pos.x >= last.x);
+            last = b.pose.pos;
+        }
+        let (_, depth) = penetration(&a, &b).unwrap();
+        assert!((depth - PENETRATION_SLOP).abs() < 1e-12);
+    }
+}
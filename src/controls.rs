@@ -0,0 +1,258 @@
+use piston::input::Key;
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "scene")]
+use std::fs;
+
+use super::r3::R3;
+
+/// Which key triggers each of the player's actions. Actions left out of a
+/// key map file keep their default key.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize), serde(default))]
+pub struct KeyBindings {
+    // held down
+    pub roll_left: Key,
+    pub roll_right: Key,
+    pub pitch_forward: Key,
+    pub pitch_back: Key,
+    pub throttle_up: Key,
+    pub throttle_down: Key,
+
+    // pressed once
+    pub brake: Key,
+    pub toggle_hud: Key,
+    pub toggle_grid: Key,
+    pub toggle_axes: Key,
+    pub toggle_pause: Key,
+    pub step_once: Key,
+    pub screenshot: Key,
+    pub export_svg: Key,
+    pub export_obj: Key,
+    pub save_scene: Key,
+    pub toggle_debug: Key,
+    pub toggle_culling: Key,
+    pub toggle_lighting: Key,
+    pub toggle_fog: Key,
+    pub toggle_projection: Key,
+    pub cycle_render_style: Key,
+    pub select_next: Key,
+    pub toggle_visible: Key,
+    pub frame_selected: Key,
+    pub more_curve_detail: Key,
+    pub less_curve_detail: Key,
+    pub more_curve_splits: Key,
+    pub fewer_curve_splits: Key,
+    pub more_distant_detail: Key,
+    pub less_distant_detail: Key,
+    pub reset_camera: Key,
+    pub zoom_in: Key,
+    pub zoom_out: Key,
+    pub store_viewpoint: [Key; 4],
+    pub recall_viewpoint: [Key; 4],
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            roll_left: Key::A,
+            roll_right: Key::D,
+            pitch_forward: Key::W,
+            pitch_back: Key::S,
+            throttle_up: Key::Space,
+            throttle_down: Key::C,
+
+            brake: Key::X,
+            toggle_hud: Key::H,
+            toggle_grid: Key::N,
+            toggle_axes: Key::K,
+            toggle_pause: Key::T,
+            step_once: Key::Y,
+            screenshot: Key::F12,
+            export_svg: Key::F11,
+            export_obj: Key::F9,
+            save_scene: Key::F10,
+            toggle_debug: Key::P,
+            toggle_culling: Key::B,
+            toggle_lighting: Key::L,
+            toggle_fog: Key::G,
+            toggle_projection: Key::V,
+            cycle_render_style: Key::F,
+            select_next: Key::Tab,
+            toggle_visible: Key::O,
+            frame_selected: Key::Z,
+            more_curve_detail: Key::LeftBracket,
+            less_curve_detail: Key::RightBracket,
+            more_curve_splits: Key::Equals,
+            fewer_curve_splits: Key::Minus,
+            more_distant_detail: Key::Quote,
+            less_distant_detail: Key::Semicolon,
+            reset_camera: Key::R,
+            zoom_in: Key::Period,
+            zoom_out: Key::Comma,
+            store_viewpoint: [Key::F1, Key::F2, Key::F3, Key::F4],
+            recall_viewpoint: [Key::D1, Key::D2, Key::D3, Key::D4],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Read a json key map, like `{ "pitch_forward": "S", "pitch_back": "W" }`
+    #[cfg(feature = "scene")]
+    pub fn load(path: &str) -> Result<KeyBindings, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("Could not parse {}: {}", path, e))
+    }
+
+    #[cfg(not(feature = "scene"))]
+    pub fn load(path: &str) -> Result<KeyBindings, String> {
+        Err(format!(
+            "Could not read {}: key maps need the scene feature",
+            path
+        ))
+    }
+}
+
+/// Which of the held down controls are pressed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeldKeys {
+    pub left: bool,
+    pub right: bool,
+    pub forward: bool,
+    pub back: bool,
+    pub up: bool,
+    pub down: bool,
+}
+
+impl HeldKeys {
+    /// Track a key going up or down. Returns false if `key` isn't bound to a
+    /// held down control.
+    pub fn set(&mut self, keys: &KeyBindings, key: Key, pressed: bool) -> bool {
+        let held = if key == keys.roll_right {
+            &mut self.right
+        } else if key == keys.roll_left {
+            &mut self.left
+        } else if key == keys.pitch_forward {
+            &mut self.forward
+        } else if key == keys.pitch_back {
+            &mut self.back
+        } else if key == keys.throttle_up {
+            &mut self.up
+        } else if key == keys.throttle_down {
+            &mut self.down
+        } else {
+            return false;
+        };
+        *held = pressed;
+        true
+    }
+}
+
+/// How mouse movement turns the camera
+#[derive(Clone, Debug)]
+pub struct MouseSettings {
+    /// Radians turned per pixel of movement
+    pub sensitivity: f64,
+    /// Moving the mouse up looks down instead of up
+    pub invert_y: bool,
+    /// Fraction of the distance to the orbit target moved per scroll step
+    pub scroll_speed: f64,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        MouseSettings {
+            sensitivity: 0.01,
+            invert_y: false,
+            scroll_speed: 0.05,
+        }
+    }
+}
+
+impl MouseSettings {
+    /// The rotation in camera space for a mouse movement
+    pub fn angular_velocity(&self, mov: [f64; 2]) -> R3 {
+        let pitch = if self.invert_y { mov[1] } else { -mov[1] };
+        R3::new(0.0, pitch, mov[0]) * self.sensitivity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebound_keys_set_their_controls() {
+        let keys = KeyBindings {
+            roll_left: Key::Left,
+            roll_right: Key::Right,
+            ..KeyBindings::default()
+        };
+        let mut held = HeldKeys::default();
+
+        assert!(held.set(&keys, Key::Left, true));
+        assert_eq!(
+            held,
+            HeldKeys {
+                left: true,
+                ..HeldKeys::default()
+            }
+        );
+        // the old key does nothing now
+        assert!(!held.set(&keys, Key::A, true));
+        assert!(held.set(&keys, Key::Left, false));
+        assert_eq!(held, HeldKeys::default());
+    }
+
+    #[cfg(feature = "scene")]
+    #[test]
+    fn partial_key_maps_keep_the_other_defaults() {
+        let keys: KeyBindings =
+            serde_json::from_str(r#"{ "pitch_forward": "S", "pitch_back": "W" }"#).unwrap();
+
+        assert_eq!(
+            keys,
+            KeyBindings {
+                pitch_forward: Key::S,
+                pitch_back: Key::W,
+                ..KeyBindings::default()
+            }
+        );
+
+        let mut held = HeldKeys::default();
+        held.set(&keys, Key::S, true);
+        assert!(held.forward && !held.back);
+    }
+
+    #[test]
+    fn invert_y_flips_only_the_pitch() {
+        let normal = MouseSettings::default();
+        let inverted = MouseSettings {
+            invert_y: true,
+            ..MouseSettings::default()
+        };
+        let mov = [3.0, -4.0];
+
+        let a = normal.angular_velocity(mov);
+        let b = inverted.angular_velocity(mov);
+        assert_eq!(b.y, -a.y);
+        assert_eq!((b.x, b.z), (a.x, a.z));
+        // moving the mouse up pitches up
+        assert!(normal.angular_velocity([0.0, -1.0]).y > 0.0);
+    }
+
+    #[test]
+    fn sensitivity_scales_the_turn() {
+        let slow = MouseSettings::default();
+        let fast = MouseSettings {
+            sensitivity: 4.0 * slow.sensitivity,
+            ..MouseSettings::default()
+        };
+        let mov = [3.0, -4.0];
+
+        assert_eq!(fast.angular_velocity(mov), slow.angular_velocity(mov) * 4.0);
+        assert_eq!(slow.angular_velocity(mov), R3::new(0.0, 0.04, 0.03));
+        assert_eq!(slow.angular_velocity([0.0, 0.0]), R3::zero());
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -8,15 +9,59 @@ use piston::input::*;
 use piston::window::{OpenGLWindow, WindowSettings};
 // use std::time::SystemTime;
 
+mod collision;
+mod controls;
 mod mesh;
 mod r3;
 use r3::*;
 mod render;
+mod replay;
+mod resource;
+use resource::resource_path;
+mod scene;
 use r3::quaternion::*;
 
+/// Length of a single physics step in seconds
+const PHYSICS_DT: f64 = 1.0 / 120.0;
+/// How long it takes to fly to a recalled viewpoint in seconds
+const VIEWPOINT_TRANSITION: f64 = 0.5;
+/// Limits on `Camera::scale`, in pixels per radian
+const MIN_SCALE: f64 = 20.0;
+const MAX_SCALE: f64 = 5000.0;
+/// How much one press of the zoom keys changes the scale by
+const ZOOM_STEP: f64 = 1.25;
+/// How quickly the scale approaches its target, per second
+const ZOOM_RATE: f64 = 10.0;
+/// How many frame times the HUD graph shows
+const FRAME_HISTORY: usize = 120;
+/// Height of the frame time graph in pixels per second
+const FRAME_GRAPH_SCALE: f64 = 1500.0;
+/// Distance between lines of the reference grid, and how many lines it
+/// reaches out from the origin in each direction
+const GRID_SPACING: f64 = 100.0;
+const GRID_EXTENT: i32 = 20;
+const GRID_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+/// Length of the axes drawn at the origin
+const AXES_LENGTH: f64 = 100.0;
+/// Radius in pixels a framed object is backed off to fit in
+const FRAME_RADIUS: f64 = 150.0;
+
 pub struct GameObject {
     mesh: mesh::Mesh,
+    /// How to build the mesh again when saving a scene, if it can be
+    spec: Option<mesh::MeshSpec>,
+    /// Radius of a sphere around `pose.pos` containing the whole mesh
+    bounding_radius: f64,
+    /// Center in local space and radius of a tighter sphere containing the
+    /// whole mesh, used to skip drawing objects that are off screen
+    bounding_sphere: (R3, f64),
     pose: pose::Pose,
+    /// The mesh's vertices moved to `pose`, or None if the pose changed since they were found
+    world_vertices: Option<Vec<R3>>,
+
+    visible: bool,
+    physics_enabled: bool,
+    integrator: Integrator,
 
     velocity: R3,
     acceleration: R3,
@@ -25,52 +70,163 @@ pub struct GameObject {
     angular_acceleration: R3,
 }
 
+/// How `GameObject::physics_step` integrates motion
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Integrator {
+    /// Semi-implicit Euler with a first order orientation update
+    Euler,
+    /// Classic fourth order Runge-Kutta for position and velocity, with
+    /// orientation advanced by the exponential of the angular velocity at the
+    /// middle of the step
+    Rk4,
+}
+
+impl Integrator {
+    /// Advance a position and velocity by `dt` seconds under an acceleration
+    /// that can depend on both
+    fn step<F>(self, pos: R3, velocity: R3, dt: f64, acceleration: F) -> (R3, R3)
+    where
+        F: Fn(R3, R3) -> R3,
+    {
+        match self {
+            Integrator::Euler => {
+                // update velocity first so orbits don't spiral outwards
+                let velocity = velocity + acceleration(pos, velocity) * dt;
+                (pos + velocity * dt, velocity)
+            }
+
+            Integrator::Rk4 => {
+                let k1 = (velocity, acceleration(pos, velocity));
+                let half = |(dp, dv): (R3, R3)| (pos + dp * (0.5 * dt), velocity + dv * (0.5 * dt));
+                let (p2, v2) = half(k1);
+                let k2 = (v2, acceleration(p2, v2));
+                let (p3, v3) = half(k2);
+                let k3 = (v3, acceleration(p3, v3));
+                let (p4, v4) = (pos + k3.0 * dt, velocity + k3.1 * dt);
+                let k4 = (v4, acceleration(p4, v4));
+
+                (
+                    pos + (k1.0 + (k2.0 + k3.0) * 2.0 + k4.0) * (dt / 6.0),
+                    velocity + (k1.1 + (k2.1 + k3.1) * 2.0 + k4.1) * (dt / 6.0),
+                )
+            }
+        }
+    }
+}
+
 impl GameObject {
-    fn physics_step(&mut self, dt: f64) {
-        self.velocity += self.acceleration * dt;
-        self.pose.pos += self.velocity * dt;
+    /// Advance the object by `dt` seconds. On top of its own acceleration it
+    /// falls with `gravity` and slows by `drag` times its velocity per second.
+    fn physics_step(&mut self, dt: f64, gravity: R3, drag: f64) {
+        let pose = self.pose;
+        let acceleration = self.acceleration + gravity;
+
+        // drag follows the velocity through each stage of the step
+        let (pos, velocity) =
+            self.integrator
+                .step(self.pose.pos, self.velocity, dt, |_, velocity| {
+                    acceleration - velocity * drag
+                });
+        self.pose.pos = pos;
+        self.velocity = velocity;
+
+        match self.integrator {
+            Integrator::Euler => {
+                self.angular_velocity += self.angular_acceleration * dt;
+                // q_next = ( 1 + 1/2 * dt * angular_velocity ) * q
+                // see https://gamedev.stackexchange.com/a/157018
+                self.pose.orientation =
+                    (Quaternion::from_real_imaginary(1.0, &(self.angular_velocity * 0.5 * dt))
+                        * self.pose.orientation)
+                        .normalized();
+            }
+
+            Integrator::Rk4 => {
+                let mid_angular_velocity =
+                    self.angular_velocity + self.angular_acceleration * (0.5 * dt);
+                let angle = mid_angular_velocity.norm() * dt;
+                if angle != 0.0 {
+                    self.pose.orientation =
+                        (Quaternion::rotation(mid_angular_velocity.normalized(), angle)
+                            * self.pose.orientation)
+                            .normalized();
+                }
+                self.angular_velocity += self.angular_acceleration * dt;
+            }
+        }
 
-        self.angular_velocity += self.angular_acceleration * dt;
-        // q_next = ( 1 + 1/2 * dt * angular_velocity ) * q
-        // see https://gamedev.stackexchange.com/a/157018
-        self.pose.orientation =
-            Quaternion::from_real_imaginary(1.0, &(self.angular_velocity * 0.5 * dt))
-                * self.pose.orientation;
+        if self.pose != pose {
+            self.world_vertices = None;
+        }
+    }
+
+    /// Whether the object is visible and might land on a screen of
+    /// `view_size` pixels, judged by its `bounding_sphere`
+    fn should_draw(&self, camera: &render::Camera, view_size: [f64; 2]) -> bool {
+        let (center, radius) = self.bounding_sphere;
+        let center = self.pose.orientation.rotate(&center) + self.pose.pos;
+        self.visible && render::sphere_on_screen(&center, radius, camera, view_size)
+    }
+
+    /// Find the mesh's vertices in world space if the pose has changed since last time
+    fn cache_world_vertices(&mut self) {
+        if self.world_vertices.is_none() {
+            self.world_vertices = Some(mesh::world_vertices(&self.mesh, &self.pose));
+        }
     }
 }
 
 pub struct App {
-    gl: GlGraphics, // OpenGL drawing backend
-    glyph_cache: GlyphCache<'static>,
-
     prev_fps_update: Instant,
     frames_since_prev_fps: i32,
     fps: f64,
+    prev_frame: Instant,
+    frame_times: VecDeque<f64>, // seconds between the most recent rendered frames, oldest first
+
+    physics_accumulator: f64, // simulation time not yet stepped
 
     // input
-    control_magnitude: f64, // size of roll control input
-    left: bool,             // input state
-    right: bool,            // input state
-    up: bool,               // input state
-    down: bool,             // input state
-    forward: bool,
-    back: bool,
+    control_magnitude: f64,   // size of roll control input
+    max_angular_rate: f64,    // fastest the camera can turn in radians per second
+    held: controls::HeldKeys, // input state
     draw_hud: bool,
+    draw_grid: bool,
+    grid: mesh::Mesh,
+    draw_axes: bool,
+    take_screenshot: bool, // save the next frame to a png once it is drawn
+    keys: controls::KeyBindings,
+    mouse_settings: controls::MouseSettings,
 
     mouse_left: bool,
     mouse_right: bool,
     mouse_mov: [f64; 2],
+    mouse_cursor: [f64; 2],
     mouse_scroll: [f64; 2],
 
     // player state
     acceleration: f64,
     velocity: f64,
+    velocity_damping: f64, // rate velocity decays at per second while not thrusting
     camera: render::Camera,
+    window_size: [f64; 2], // as of the last frame drawn
+    initial_camera: render::Camera,
+    viewpoints: [Option<render::Camera>; 4],
+    camera_transition: Option<(render::Camera, render::Camera, f64)>, // from, to, seconds elapsed
+    orbit_target: R3, // point the camera circles when dragging with the right mouse button
+    target_scale: f64, // camera scale being zoomed towards
+    recorder: Option<replay::Recorder>, // saves every input when running with `--record`
+    loading: Option<scene::SceneLoad>, // a scene from `--scene` whose meshes aren't all in yet
+    load_error: Option<String>, // why the last scene failed to load, shown on the HUD
 
     // game objects
     objects: Vec<GameObject>,
+    selected: Option<usize>,
+    paused: bool,     // objects stay put while the camera still moves
+    restitution: f64, // fraction of approach speed kept when objects bounce, 1 is perfectly elastic
+    gravity: R3,      // acceleration applied to every object with physics
+    drag: f64,        // fraction of an object's velocity lost per second
     // Game state
-    debug: bool,
+    render_options: render::RenderOptions,
     // in_cube: bool,
     // score: i32,
     // last_score: SystemTime,
@@ -78,10 +234,11 @@ pub struct App {
 }
 
 fn initial_app(
-    gl: GlGraphics,
+    screen_height: f64,
     control_magnitude: f64,
     acceleration: f64,
     velocity: f64,
+    demo: &str,
     // last_score: SystemTime,
     // timeout_sec: u32,
 ) -> App {
@@ -94,9 +251,25 @@ fn initial_app(
             ),
         };
 
+        let spec = mesh::MeshSpec {
+            source: mesh::MeshSource::Primitive(mesh::Primitive::Cuboid {
+                size: R3::new(100.0, 100.0, 100.0),
+            }),
+            color: [0.5, 0.0, 0.5, 1.0],
+            scale: 1.0,
+        };
+        let mesh = spec.build().unwrap_or_else(|e| panic!("{}", e));
+
         GameObject {
-            mesh: mesh::cuboid(R3::new(100.0, 100.0, 100.0), [0.5, 0.0, 0.5, 1.0]),
+            bounding_radius: mesh.bounding_radius(),
+            bounding_sphere: mesh.bounding_sphere(),
+            mesh,
+            spec: Some(spec),
             pose: pose.rotate(R3::zero(), rotation),
+            world_vertices: None,
+            visible: true,
+            physics_enabled: true,
+            integrator: Integrator::Euler,
 
             acceleration: R3::zero(),
             velocity: R3::zero(),
@@ -113,11 +286,23 @@ fn initial_app(
             orientation: Quaternion::zero_rotation(),
         };
 
-        let mesh = mesh::mk_meshes("data/octahedron.obj", [0.0, 0.33, 0.67, 1.0]).unwrap();
+        let spec = mesh::MeshSpec {
+            source: mesh::MeshSource::File("data/octahedron.obj".to_string()),
+            color: [0.0, 0.33, 0.67, 1.0],
+            scale: 0.25,
+        };
+        let mesh = spec.build().unwrap_or_else(|e| panic!("{}", e));
 
         GameObject {
-            mesh: mesh::scale(mesh, 0.25),
+            bounding_radius: mesh.bounding_radius(),
+            bounding_sphere: mesh.bounding_sphere(),
+            mesh,
+            spec: Some(spec),
             pose: pose.rotate(R3::zero(), rotation),
+            world_vertices: None,
+            visible: true,
+            physics_enabled: true,
+            integrator: Integrator::Euler,
 
             acceleration: R3::zero(),
             velocity: R3::zero(),
@@ -134,53 +319,92 @@ fn initial_app(
             orientation: Quaternion::zero_rotation(),
         };
 
-        let mesh = mesh::mk_meshes("data/diamond.obj", [0.0, 0.5, 0.5, 1.0]).unwrap();
+        let spec = mesh::MeshSpec {
+            source: mesh::MeshSource::File("data/diamond.obj".to_string()),
+            color: [0.0, 0.5, 0.5, 1.0],
+            scale: 1.0,
+        };
+        let mesh = spec.build().unwrap_or_else(|e| panic!("{}", e));
 
         GameObject {
+            bounding_radius: mesh.bounding_radius(),
+            bounding_sphere: mesh.bounding_sphere(),
             mesh,
+            spec: Some(spec),
             pose: pose.rotate(R3::zero(), rotation),
+            world_vertices: None,
+            visible: true,
+            physics_enabled: true,
+            integrator: Integrator::Euler,
 
             acceleration: R3::zero(),
             velocity: R3::zero(),
 
             angular_acceleration: rotation.rotate(&R3::new(0.0, 0.0, -0.0)),
-            angular_velocity: rotation.rotate(&R3::new(0.0, 0.0, -1.0)),
+            angular_velocity: rotation.rotate(&-R3::UP),
         }
     }
 
     fn teapot(rotation: Quaternion) -> GameObject {
         let pose = pose::Pose {
             pos: R3::new(5.0, 0.0, 2.0),
-            orientation: Quaternion::rotation(R3::new(0.0, 0.0, -1.0), 0.5 * core::f64::consts::PI)
-                * Quaternion::rotation(R3::new(-1.0, 0.0, 0.0), 0.5 * core::f64::consts::PI),
+            orientation: Quaternion::from_euler(
+                -0.5 * core::f64::consts::PI,
+                0.0,
+                -0.5 * core::f64::consts::PI,
+            ),
         };
 
-        let mesh = mesh::mk_meshes("data/teapot.obj", [0.0, 0.5, 0.5, 1.0]).unwrap();
+        let spec = mesh::MeshSpec {
+            source: mesh::MeshSource::File("data/teapot.obj".to_string()),
+            color: [0.0, 0.5, 0.5, 1.0],
+            scale: 1.0,
+        };
+        let mesh = spec.build().unwrap_or_else(|e| panic!("{}", e));
 
         GameObject {
-            mesh: mesh::condense_mesh(&mesh),
+            bounding_radius: mesh.bounding_radius(),
+            bounding_sphere: mesh.bounding_sphere(),
+            mesh,
+            spec: Some(spec),
             pose: pose.rotate(R3::zero(), rotation),
+            world_vertices: None,
+            visible: true,
+            physics_enabled: true,
+            integrator: Integrator::Euler,
 
             acceleration: R3::zero(),
             velocity: R3::zero(),
 
             angular_acceleration: rotation.rotate(&R3::new(0.0, 0.0, -0.0)),
-            angular_velocity: rotation.rotate(&R3::new(0.0, 0.0, -1.0)),
+            angular_velocity: rotation.rotate(&-R3::UP),
         }
     }
 
     fn ship(rotation: Quaternion) -> GameObject {
         let pose = pose::Pose {
             pos: R3::new(0.0, 0.0, 0.0),
-            orientation: Quaternion::rotation(R3::new(0.0, 0.0, -1.0), 0.5 * core::f64::consts::PI),
+            orientation: Quaternion::rotation(-R3::UP, 0.5 * core::f64::consts::PI),
         };
 
-        let mesh = mesh::mk_meshes("models/hole-ship-wire.obj", [0.0, 0.5, 0.5, 1.0]).unwrap();
+        let spec = mesh::MeshSpec {
+            source: mesh::MeshSource::File("models/hole-ship-wire.obj".to_string()),
+            color: [0.0, 0.5, 0.5, 1.0],
+            scale: 1.0,
+        };
+        let mesh = spec.build().unwrap_or_else(|e| panic!("{}", e));
         // println!("{:?}", mesh::condense_mesh(&mesh));
 
         GameObject {
-            mesh: mesh::condense_mesh(&mesh),
+            bounding_radius: mesh.bounding_radius(),
+            bounding_sphere: mesh.bounding_sphere(),
+            mesh,
+            spec: Some(spec),
             pose: pose.rotate(R3::zero(), rotation),
+            world_vertices: None,
+            visible: true,
+            physics_enabled: true,
+            integrator: Integrator::Euler,
 
             acceleration: R3::zero(),
             velocity: R3::zero(),
@@ -190,54 +414,85 @@ fn initial_app(
         }
     }
 
+    use std::f64::consts::PI;
+    let around = |angle: f64| Quaternion::rotation(R3::new(0.0, 1.0, 0.0), angle);
+    let objects = match demo {
+        "octahedron" => vec![octahedron(Quaternion::zero_rotation())],
+        "cube" => vec![cube(Quaternion::zero_rotation())],
+        "cubes" => vec![
+            cube(around(0.0)),
+            cube(around((2.0 / 3.0) * PI)),
+            cube(around(-(2.0 / 3.0) * PI)),
+        ],
+        "teapots" => vec![
+            teapot(around(0.0)),
+            teapot(around((2.0 / 3.0) * PI)),
+            teapot(around(-(2.0 / 3.0) * PI)),
+        ],
+        "diamond" => vec![diamond(around(-(2.0 / 3.0) * PI))],
+        "ship" => vec![ship(Quaternion::zero_rotation())],
+        _ => panic!("Unknown demo {}", demo),
+    };
+
+    let camera_position = R3::new(-30.0, 0.0, -30.0);
     let camera = render::Camera {
-        position: R3::new(-30.0, 0.0, -30.0),
-        orientation: Quaternion::rotation(R3::new(0.0, -1.0, 0.0), 0.25 * core::f64::consts::PI),
-        scale: 1080.0 / std::f64::consts::PI / 2.0,
+        position: camera_position,
+        orientation: Quaternion::look_at(-camera_position, -R3::UP),
+        scale: screen_height / std::f64::consts::PI / 2.0,
+        projection: render::ProjectionMode::Angular,
     };
 
     App {
-        gl,
-        glyph_cache: GlyphCache::new("OpenSans-Regular.ttf", (), TextureSettings::new()).unwrap(),
-
         prev_fps_update: Instant::now(),
         frames_since_prev_fps: 0,
         fps: 0.0,
+        prev_frame: Instant::now(),
+        frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+
+        physics_accumulator: 0.0,
 
         control_magnitude,
-        left: false,
-        right: false,
-        up: false,
-        down: false,
-        forward: false,
-        back: false,
+        max_angular_rate: 2.0 * std::f64::consts::PI,
+        held: controls::HeldKeys::default(),
         draw_hud: true,
+        draw_grid: false,
+        grid: mesh::grid(
+            2.0 * GRID_SPACING * GRID_EXTENT as f64,
+            2 * GRID_EXTENT as usize,
+            GRID_COLOR,
+        ),
+        draw_axes: false,
+        take_screenshot: false,
+        keys: controls::KeyBindings::default(),
+        mouse_settings: controls::MouseSettings::default(),
 
         mouse_left: false,
         mouse_right: false,
         mouse_mov: [0.0, 0.0],
+        mouse_cursor: [0.0, 0.0],
         mouse_scroll: [0.0, 0.0],
 
         acceleration,
         velocity,
+        velocity_damping: 0.5,
         camera,
-
-        objects: vec![
-            // cube(Quaternion::rotation(R3::new(0.0, 1.0, 0.0), 0.0 * core::f64::consts::PI)),
-            // cube(Quaternion::rotation(R3::new(0.0, 1.0, 0.0), (2.0/3.0) * core::f64::consts::PI)),
-            // cube(Quaternion::rotation(R3::new(0.0, 1.0, 0.0), -(2.0/3.0) * core::f64::consts::PI)),
-
-            // teapot(Quaternion::rotation(R3::new(0.0, 1.0, 0.0), 0.0 * core::f64::consts::PI)),
-            // teapot(Quaternion::rotation(R3::new(0.0, 1.0, 0.0), (2.0/3.0) * core::f64::consts::PI)),
-            // teapot(Quaternion::rotation(R3::new(0.0, 1.0, 0.0), -(2.0/3.0) * core::f64::consts::PI)),
-
-            // diamond(Quaternion::rotation(R3::new(0.0, 1.0, 0.0), -(2.0/3.0) * core::f64::consts::PI)),
-
-            // cube(Quaternion::zero_rotation()),
-            octahedron(Quaternion::zero_rotation()),
-            // ship(Quaternion::zero_rotation()),
-        ],
-        debug: false,
+        window_size: [0.0, 0.0],
+        initial_camera: camera,
+        viewpoints: [None; 4],
+        camera_transition: None,
+        orbit_target: R3::zero(),
+        target_scale: camera.scale,
+
+        objects,
+        selected: None,
+        recorder: None,
+        loading: None,
+        load_error: None,
+        paused: false,
+        restitution: 1.0,
+        gravity: R3::zero(),
+        drag: 0.0,
+        render_options: render::RenderOptions::default(),
         // in_cube: false,
         // score: 0,
         // last_score,
@@ -246,42 +501,114 @@ fn initial_app(
 }
 
 impl App {
-    fn render(&mut self, args: RenderArgs) {
+    /// Draw the scene and HUD with `gl`, the OpenGL drawing backend
+    fn render(&mut self, args: RenderArgs, gl: &mut GlGraphics, glyph_cache: &mut GlyphCache) {
         use graphics::*;
 
         const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
         // const RED:   [f32; 4] = [1.0, 0.0, 0.0, 1.0];
         const BLUE: [f32; 4] = [0.0, 0.5, 1.0, 1.0];
+        const SELECTED: [f32; 4] = [1.0, 0.8, 0.0, 1.0];
+        const FRAME_TARGET: [f32; 4] = [0.0, 0.25, 0.5, 1.0];
         // const OUT:   [f32; 4] = [0.5, 0.0, 0.5, 1.0];
         // const IN:    [f32; 4] = [0.0, 0.25, 0.5, 1.0];
 
-        let (x, y) = (args.window_size[0] / 2.0, args.window_size[1] / 2.0);
         let camera = self.camera;
+        // only pay for moving the vertices of objects that will be drawn
+        for obj in self.objects.iter_mut() {
+            if obj.should_draw(&camera, args.window_size) {
+                obj.cache_world_vertices();
+            }
+        }
+
+        push_frame_time(
+            &mut self.frame_times,
+            self.prev_frame.elapsed().as_secs_f64(),
+        );
+        self.prev_frame = Instant::now();
+
+        self.window_size = args.window_size;
+        let (x, y) = (args.window_size[0] / 2.0, args.window_size[1] / 2.0);
         let draw_hud = self.draw_hud;
+        let draw_grid = self.draw_grid;
+        let grid = &self.grid;
+        let draw_axes = self.draw_axes;
         let objects = &self.objects;
-        let glyph_cache = &mut self.glyph_cache;
         let fps = self.fps;
-        let debug = self.debug;
+        let frame_times = &self.frame_times;
+        let speed = self.velocity;
+        let render_options = self.render_options;
+        let selected = self.selected;
+        let window_size = args.window_size;
+        let status = match (&self.loading, &self.load_error) {
+            (Some(loading), _) => {
+                let (loaded, total) = loading.progress();
+                Some(format!("loading scene: {}/{} meshes", loaded, total))
+            }
+            (None, Some(e)) => Some(e.clone()),
+            (None, None) => None,
+        };
 
-        self.gl.draw(args.viewport(), |c, gl| {
+        gl.draw(args.viewport(), |c, gl| {
             // Clear the screen.
             clear(BLACK, gl);
 
-            for obj in objects {
+            if draw_grid {
+                render::render_grid(
+                    grid,
+                    &render_options,
+                    camera,
+                    &c,
+                    gl,
+                    c.transform.trans(x, y),
+                );
+            }
+            if draw_axes {
+                render::render_axes(
+                    AXES_LENGTH,
+                    &render_options,
+                    camera,
+                    &c,
+                    gl,
+                    c.transform.trans(x, y),
+                );
+            }
+
+            for (i, obj) in objects
+                .iter()
+                .enumerate()
+                .filter(|(_, obj)| obj.should_draw(&camera, window_size))
+            {
                 mesh::render_mesh(
                     &obj.mesh,
-                    &obj.pose,
-                    debug,
+                    obj.world_vertices
+                        .as_deref()
+                        .expect("world vertices are cached before drawing"),
+                    &render_options,
+                    if selected == Some(i) {
+                        Some(SELECTED)
+                    } else {
+                        None
+                    },
                     &c,
                     gl,
                     camera,
                     c.transform.trans(x, y),
                 );
+                if render_options.debug {
+                    render::render_object_gizmo(
+                        obj,
+                        &render_options,
+                        camera,
+                        &c,
+                        gl,
+                        c.transform.trans(x, y),
+                    );
+                }
             }
 
             if draw_hud {
                 // render some HUD stuff
-                // TODO: Figure out how big the screen actually is
                 Line::new(BLUE, 1.0).draw(
                     [0.0, -5.0, 0.0, 5.0],
                     &c.draw_state,
@@ -294,23 +621,16 @@ impl App {
                     c.transform.trans(x, y),
                     gl,
                 );
-                Ellipse::new_border(BLUE, 0.5).draw(
-                    rectangle::square(-269.5, -269.5, 539.0),
-                    &c.draw_state,
-                    c.transform.trans(x, y),
-                    gl,
-                );
-                Ellipse::new_border(BLUE, 1.0).draw(
-                    rectangle::square(-539.0, -539.0, 1078.0),
-                    &c.draw_state,
-                    c.transform.trans(x, y),
-                    gl,
-                );
+                for (rect, border) in hud_rings(&camera) {
+                    Ellipse::new_border(BLUE, border).draw(
+                        rect,
+                        &c.draw_state,
+                        c.transform.trans(x, y),
+                        gl,
+                    );
+                }
 
-                let info = format!(
-                    "FPS: {:.2}\ncamera position: ({:.2}, {:.2}, {:.2})",
-                    fps, camera.position.x, camera.position.y, camera.position.z
-                );
+                let info = hud_info(fps, &camera, speed, objects, selected, status.as_deref());
 
                 for (i, line) in info.lines().enumerate() {
                     Text::new_color(BLUE, 14)
@@ -323,28 +643,46 @@ impl App {
                         )
                         .unwrap();
                 }
+
+                // frame times along the bottom left, with a line at 60 fps
+                let bottom = args.window_size[1] - 10.0;
+                let width = 2.0 * FRAME_HISTORY as f64;
+                let target = bottom - FRAME_GRAPH_SCALE / 60.0;
+                Line::new(FRAME_TARGET, 1.0).draw(
+                    [10.0, target, 10.0 + width, target],
+                    &c.draw_state,
+                    c.transform,
+                    gl,
+                );
+                let points: Vec<[f64; 2]> = frame_times
+                    .iter()
+                    .enumerate()
+                    .map(|(i, dt)| [10.0 + 2.0 * i as f64, bottom - FRAME_GRAPH_SCALE * dt])
+                    .collect();
+                for pair in points.windows(2) {
+                    Line::new(BLUE, 1.0).draw(
+                        [pair[0][0], pair[0][1], pair[1][0], pair[1][1]],
+                        &c.draw_state,
+                        c.transform,
+                        gl,
+                    );
+                }
             }
         });
+
+        if self.take_screenshot {
+            self.take_screenshot = false;
+            save_screenshot(args.draw_size);
+        }
     }
 
     fn update(&mut self, args: UpdateArgs) {
-        const FORWARD: R3 = R3 {
-            x: 1.0,
-            y: 0.0,
-            z: 0.0,
-        };
-
-        const RIGHT: R3 = R3 {
-            x: 0.0,
-            y: 1.0,
-            z: 0.0,
-        };
-
         // move the camera with the mouse
-        if self.mouse_right && self.mouse_mov != [0.0, 0.0] {
-            let speed = 0.01;
-
-            let angular_velocity = R3::new(0.0, -self.mouse_mov[1], self.mouse_mov[0]) * speed;
+        let angular_velocity = limit_rotation(
+            self.mouse_settings.angular_velocity(self.mouse_mov),
+            self.max_angular_rate * args.dt,
+        );
+        if self.mouse_right && angular_velocity != R3::zero() {
             let axis = self
                 .camera
                 .orientation
@@ -352,90 +690,242 @@ impl App {
             let angle = angular_velocity.norm();
             let rotation = Quaternion::rotation(axis, angle);
 
-            self.camera.position = rotation.rotate(&self.camera.position);
-            self.camera.orientation = rotation * self.camera.orientation;
+            let pose = pose::Pose {
+                pos: self.camera.position,
+                orientation: self.camera.orientation,
+            }
+            .rotate(self.orbit_target, rotation);
+            self.camera.position = pose.pos;
+            self.camera.orientation = pose.orientation;
         }
         self.mouse_mov = [0.0, 0.0];
 
         if self.mouse_scroll[1] != 0.0 {
-            let distance = self.camera.position.norm();
-            let speed = 0.05;
+            let to_target = self.orbit_target - self.camera.position;
+            let distance = to_target.norm();
+            let velocity = self.mouse_scroll[1] * distance * self.mouse_settings.scroll_speed;
 
-            let velocity = self.mouse_scroll[1] * distance * speed;
-
-            self.camera.position += self.camera.orientation.rotate(&R3::new(velocity, 0.0, 0.0));
+            if distance > 0.0 {
+                self.camera.position += to_target * (velocity / distance);
+            }
         }
         self.mouse_scroll = [0.0, 0.0];
 
+        // swap in a loading scene once all of its meshes are ready
+        if let Some(loading) = &mut self.loading {
+            match loading.poll() {
+                Ok(None) => {}
+                Ok(Some(objects)) => {
+                    self.objects = objects;
+                    self.selected = None;
+                    self.loading = None;
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    self.load_error = Some(e);
+                    self.loading = None;
+                }
+            }
+        }
+
+        // advance the simulation in fixed steps so it is stable regardless of frame rate
+        self.physics_accumulator += args.dt;
+        while self.physics_accumulator >= PHYSICS_DT {
+            self.step(PHYSICS_DT);
+            self.physics_accumulator -= PHYSICS_DT;
+        }
+
+        if let Some((from, to, elapsed)) = self.camera_transition {
+            let elapsed = elapsed + args.dt;
+            if elapsed >= VIEWPOINT_TRANSITION {
+                self.camera = to;
+                self.camera_transition = None;
+            } else {
+                // ease in and out
+                let t = elapsed / VIEWPOINT_TRANSITION;
+                self.camera = from.interpolate(&to, t * t * (3.0 - 2.0 * t));
+                self.camera_transition = Some((from, to, elapsed));
+            }
+        }
+
+        self.camera.scale = approach(self.camera.scale, self.target_scale, ZOOM_RATE, args.dt);
+
+        update_fps(self);
+
+        // let was_inside = self.in_cube;
+        // self.in_cube = collision::contains(
+        //     &R3 {
+        //         x: 0.0,
+        //         y: 0.0,
+        //         z: 0.0,
+        //     },
+        //     &R3 {
+        //         x: 100.0,
+        //         y: 100.0,
+        //         z: 100.0,
+        //     },
+        //     &self.camera.position,
+        // );
+        // if was_inside && !self.in_cube {
+        //     self.velocity += self.acceleration * 4.0;
+        //     // self.camera.position = self.camera.position + R3{x: 200.0, y: 0.0, z: 0.0};
+        // }
+    }
+
+    /// Advance the camera controls and, unless paused, all the objects by one
+    /// fixed time step
+    fn step(&mut self, dt: f64) {
         // pitch
         let pitch_rate = {
-            if self.forward && !self.back {
+            if self.held.forward && !self.held.back {
                 -self.control_magnitude
-            } else if !self.forward && self.back {
+            } else if !self.held.forward && self.held.back {
                 self.control_magnitude
             } else {
                 0.0
             }
         };
-        let o1 = self.camera.orientation * Quaternion::rotation(RIGHT, pitch_rate * args.dt);
 
         // roll
         let roll_rate = {
-            if self.right && !self.left {
+            if self.held.right && !self.held.left {
                 -self.control_magnitude
-            } else if !self.right && self.left {
+            } else if !self.held.right && self.held.left {
                 self.control_magnitude
             } else {
                 0.0
             }
         };
+        let rates = limit_rotation(R3::new(roll_rate, pitch_rate, 0.0), self.max_angular_rate);
+
+        let o1 = self.camera.orientation * Quaternion::rotation(R3::RIGHT, rates.y * dt);
         // rotate around the new forward vector to keep them orthogonal
-        let orientation = o1 * Quaternion::rotation(FORWARD, roll_rate * args.dt);
+        let orientation = (o1 * Quaternion::rotation(R3::FORWARD, rates.x * dt)).normalized();
 
         // speed
         let a = {
-            if self.up && !self.down {
+            if self.held.up && !self.held.down {
                 -self.acceleration
-            } else if !self.up && self.down {
+            } else if !self.held.up && self.held.down {
                 self.acceleration
             } else {
                 0.0
             }
         };
-        self.velocity += a * args.dt;
+        self.velocity += a * dt;
+        if a == 0.0 {
+            // glide to a stop when not thrusting
+            self.velocity = damp(self.velocity, self.velocity_damping, dt);
+        }
 
-        let forward = orientation.rotate(&FORWARD);
+        let forward = orientation.rotate(&R3::FORWARD);
 
         self.camera = render::Camera {
-            position: self.camera.position + forward * self.velocity * args.dt,
+            position: self.camera.position + forward * self.velocity * dt,
             orientation,
-            scale: self.camera.scale,
+            ..self.camera
         };
 
-        for obj in self.objects.iter_mut() {
-            obj.physics_step(args.dt);
+        if !self.paused {
+            self.step_objects(dt);
         }
+    }
 
-        update_fps(self);
+    /// Advance all the objects with physics by one fixed time step and bounce
+    /// any that collide
+    fn step_objects(&mut self, dt: f64) {
+        for obj in self.objects.iter_mut().filter(|obj| obj.physics_enabled) {
+            obj.physics_step(dt, self.gravity, self.drag);
+        }
 
-        // let was_inside = self.in_cube;
-        // self.in_cube = inside(
-        //     &R3 {
-        //         x: 0.0,
-        //         y: 0.0,
-        //         z: 0.0,
-        //     },
-        //     &R3 {
-        //         x: 100.0,
-        //         y: 100.0,
-        //         z: 100.0,
-        //     },
-        //     &self.camera.position,
-        // );
-        // if was_inside && !self.in_cube {
-        //     self.velocity += self.acceleration * 4.0;
-        //     // self.camera.position = self.camera.position + R3{x: 200.0, y: 0.0, z: 0.0};
-        // }
+        for (i, j) in self.collisions() {
+            let (before, after) = self.objects.split_at_mut(j);
+            let (a, b) = (&mut before[i], &mut after[0]);
+            if collision::collide(a, b) {
+                collision::resolve_collision(a, b, self.restitution);
+            }
+        }
+    }
+
+    /// Save the current camera in one of the viewpoint slots
+    fn store_viewpoint(&mut self, slot: usize) {
+        self.viewpoints[slot] = Some(self.camera);
+    }
+
+    /// Stop and move the camera back to a saved viewpoint, flying there if
+    /// `smooth` is set. Does nothing if the slot is empty.
+    fn recall_viewpoint(&mut self, slot: usize, smooth: bool) {
+        if let Some(camera) = self.viewpoints[slot] {
+            self.move_camera_to(camera, smooth);
+        }
+    }
+
+    fn move_camera_to(&mut self, camera: render::Camera, smooth: bool) {
+        self.velocity = 0.0;
+        self.target_scale = camera.scale;
+        if smooth {
+            self.camera_transition = Some((self.camera, camera, 0.0));
+        } else {
+            self.camera = camera;
+            self.camera_transition = None;
+        }
+    }
+
+    /// Pairs of objects with physics whose world space bounding boxes overlap,
+    /// with the smaller index first. This is only a broad phase check, the
+    /// objects themselves might not touch.
+    fn collisions(&self) -> Vec<(usize, usize)> {
+        let boxes: Vec<_> = self
+            .objects
+            .iter()
+            .map(|obj| {
+                if obj.physics_enabled {
+                    collision::world_aabb(obj)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut pairs = Vec::new();
+        for (j, b) in boxes.iter().enumerate() {
+            for (i, a) in boxes[..j].iter().enumerate() {
+                if let (Some(a), Some(b)) = (a, b) {
+                    if collision::aabbs_overlap(*a, *b) {
+                        pairs.push((i, j));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    /// The index of the first object hit by a ray, if any
+    fn pick(&self, origin: &R3, direction: &R3) -> Option<usize> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|(_, obj)| obj.visible)
+            .filter_map(|(i, obj)| {
+                mesh::raycast(&obj.mesh, &obj.pose, origin, direction).map(|(t, _)| (t, i))
+            })
+            .fold(None, |nearest, (t, i)| match nearest {
+                Some((nearest_t, _)) if nearest_t <= t => nearest,
+                _ => Some((t, i)),
+            })
+            .map(|(_, i)| i)
+    }
+
+    /// React to any input, recording it first if a recorder is running
+    fn input(&mut self, event: replay::InputEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(event);
+        }
+        match event {
+            replay::InputEvent::Button(args) => self.button(args),
+            replay::InputEvent::Motion(args) => self.mouse(args),
+            replay::InputEvent::Update(args) => self.update(args),
+        }
     }
 
     fn button(&mut self, args: ButtonArgs) {
@@ -445,38 +935,151 @@ impl App {
         };
 
         match args.button {
-            Button::Mouse(MouseButton::Left) => self.mouse_left = pressed,
-            Button::Mouse(MouseButton::Right) => self.mouse_right = pressed,
-
-            Button::Keyboard(Key::D) => self.right = pressed,
-            Button::Keyboard(Key::A) => self.left = pressed,
-            Button::Keyboard(Key::W) => self.forward = pressed,
-            Button::Keyboard(Key::S) => self.back = pressed,
-            Button::Keyboard(Key::Space) => self.up = pressed,
-            Button::Keyboard(Key::C) => self.down = pressed,
-            Button::Keyboard(Key::H) => {
+            Button::Mouse(MouseButton::Left) => {
+                self.mouse_left = pressed;
                 if pressed {
-                    self.draw_hud = !self.draw_hud;
+                    // select whatever is under the cursor
+                    let (origin, direction) = self.camera.screen_to_ray([
+                        self.mouse_cursor[0] - 0.5 * self.window_size[0],
+                        self.mouse_cursor[1] - 0.5 * self.window_size[1],
+                    ]);
+                    self.selected = self.pick(&origin, &direction);
+                    if let Some(i) = self.selected {
+                        self.orbit_target = self.objects[i].pose.pos;
+                    }
                 }
             }
-            Button::Keyboard(Key::X) => {
-                if pressed {
-                    self.velocity = 0.0;
-                }
+            Button::Mouse(MouseButton::Right) => self.mouse_right = pressed,
+
+            Button::Keyboard(key) => self.key(key, pressed),
+            // Button::Keyboard(Key::LShift) => {},
+            _ => {}
+        }
+    }
+
+    fn key(&mut self, key: Key, pressed: bool) {
+        let keys = &self.keys;
+
+        if self.held.set(keys, key, pressed) {
+            // held down keys just track their state
+        } else if !pressed {
+            // the rest only act when the key goes down
+        } else if key == keys.toggle_hud {
+            self.draw_hud = !self.draw_hud
+        } else if key == keys.toggle_grid {
+            self.draw_grid = !self.draw_grid
+        } else if key == keys.toggle_axes {
+            self.draw_axes = !self.draw_axes
+        } else if key == keys.toggle_pause {
+            self.paused = !self.paused
+        } else if key == keys.step_once {
+            if self.paused {
+                self.step_objects(PHYSICS_DT);
             }
-            Button::Keyboard(Key::P) => {
-                if pressed {
-                    self.debug = !self.debug;
+        } else if key == keys.screenshot {
+            self.take_screenshot = true
+        } else if key == keys.export_svg {
+            let path = timestamped("wireframe", "svg");
+            match render::export_svg(
+                &self.objects,
+                self.camera,
+                self.window_size,
+                &self.render_options,
+                &path,
+            ) {
+                Ok(()) => println!("Saved {}", path),
+                Err(e) => println!("{}", e),
+            }
+        } else if key == keys.export_obj {
+            if let Some(i) = self.selected {
+                let obj = &self.objects[i];
+                let path = timestamped("mesh", "obj");
+                match mesh::write_obj(&mesh::transform(obj.mesh.clone(), &obj.pose), &path) {
+                    Ok(()) => println!("Saved {}", path),
+                    Err(e) => println!("{}", e),
                 }
             }
-            // Button::Keyboard(Key::LShift) => {},
-            _ => {}
+        } else if key == keys.save_scene {
+            let path = timestamped("scene", "json");
+            match scene::write_scene(&path, &scene::capture(self.camera, &self.objects)) {
+                Ok(()) => println!("Saved {}", path),
+                Err(e) => println!("{}", e),
+            }
+        } else if key == keys.toggle_culling {
+            self.render_options.cull_backfaces = !self.render_options.cull_backfaces
+        } else if key == keys.toggle_lighting {
+            self.render_options.light_dir = match self.render_options.light_dir {
+                Some(_) => None,
+                None => Some(R3::new(1.0, 0.5, 2.0)),
+            }
+        } else if key == keys.toggle_fog {
+            self.render_options.fog = match self.render_options.fog {
+                Some(_) => None,
+                None => Some(render::Fog {
+                    color: [0.0, 0.0, 0.0, 1.0],
+                    start: 50.0,
+                    end: 500.0,
+                }),
+            }
+        } else if key == keys.more_curve_detail {
+            // curves split until their points are `resolution` pixels apart
+            self.render_options.resolution = (self.render_options.resolution * 0.5).max(2.5)
+        } else if key == keys.less_curve_detail {
+            self.render_options.resolution = (self.render_options.resolution * 2.0).min(320.0)
+        } else if key == keys.fewer_curve_splits {
+            self.render_options.max_split = (self.render_options.max_split - 1).max(0)
+        } else if key == keys.more_curve_splits {
+            self.render_options.max_split = (self.render_options.max_split + 1).min(16)
+        } else if key == keys.more_distant_detail {
+            // objects smaller than `lod_falloff` pixels start losing splits
+            self.render_options.lod_falloff = (self.render_options.lod_falloff * 0.5).max(25.0)
+        } else if key == keys.less_distant_detail {
+            self.render_options.lod_falloff = (self.render_options.lod_falloff * 2.0).min(6400.0)
+        } else if key == keys.toggle_projection {
+            self.camera.projection = match self.camera.projection {
+                render::ProjectionMode::Angular => render::ProjectionMode::Perspective {
+                    fov: 0.5 * std::f64::consts::PI,
+                },
+                render::ProjectionMode::Perspective { .. } => render::ProjectionMode::Angular,
+            }
+        } else if key == keys.cycle_render_style {
+            self.render_options.style = self.render_options.style.next()
+        } else if key == keys.select_next {
+            self.selected = match self.selected {
+                _ if self.objects.is_empty() => None,
+                Some(i) => Some((i + 1) % self.objects.len()),
+                None => Some(0),
+            }
+        } else if key == keys.toggle_visible {
+            if let Some(i) = self.selected {
+                self.objects[i].visible = !self.objects[i].visible;
+            }
+        } else if key == keys.brake {
+            self.velocity = 0.0
+        } else if key == keys.toggle_debug {
+            self.render_options.debug = !self.render_options.debug
+        } else if key == keys.zoom_in {
+            self.target_scale = (self.target_scale * ZOOM_STEP).min(MAX_SCALE)
+        } else if key == keys.zoom_out {
+            self.target_scale = (self.target_scale / ZOOM_STEP).max(MIN_SCALE)
+        } else if key == keys.frame_selected {
+            if let Some(i) = self.selected {
+                let target = frame_object(&self.camera, &self.objects[i]);
+                self.move_camera_to(target, true);
+                self.orbit_target = self.objects[i].pose.pos;
+            }
+        } else if key == keys.reset_camera {
+            self.move_camera_to(self.initial_camera, true)
+        } else if let Some(slot) = keys.store_viewpoint.iter().position(|&k| k == key) {
+            self.store_viewpoint(slot)
+        } else if let Some(slot) = keys.recall_viewpoint.iter().position(|&k| k == key) {
+            self.recall_viewpoint(slot, true)
         }
     }
 
     fn mouse(&mut self, args: Motion) {
         match args {
-            // Motion::MouseCursor([a, b]) => dbg!(args),
+            Motion::MouseCursor(pos) => self.mouse_cursor = pos,
             Motion::MouseRelative(mov) => {
                 self.mouse_mov[0] += mov[0];
                 self.mouse_mov[1] += mov[1];
@@ -492,6 +1095,15 @@ impl App {
     }
 }
 
+/// Record how long a frame took, dropping the oldest once there are more
+/// than `FRAME_HISTORY`
+fn push_frame_time(frame_times: &mut VecDeque<f64>, seconds: f64) {
+    if frame_times.len() >= FRAME_HISTORY {
+        frame_times.pop_front();
+    }
+    frame_times.push_back(seconds);
+}
+
 /// Update the fps stats stored in app
 fn update_fps(app: &mut App) {
     const MIN_FPS_UPDATE_INTERVAL_SECONDS: Duration = Duration::new(1, 0);
@@ -507,24 +1119,182 @@ fn update_fps(app: &mut App) {
     }
 }
 
-// fn inside(corner: &R3, size: &R3, pos: &R3) -> bool {
-//     pos.x > corner.x
-//         && pos.x < corner.x + size.x
-//         && pos.y > corner.y
-//         && pos.y < corner.y + size.y
-//         && pos.z > corner.z
-//         && pos.z < corner.z + size.z
-// }
+/// The lines of text in the corner of the HUD
+fn hud_info(
+    fps: f64,
+    camera: &render::Camera,
+    speed: f64,
+    objects: &[GameObject],
+    selected: Option<usize>,
+    status: Option<&str>,
+) -> String {
+    let heading = camera.orientation.rotate(&R3::FORWARD);
+    let (roll, pitch, yaw) = camera.orientation.to_euler();
+    let mut info = format!(
+        "FPS: {:.2}\n\
+         camera position: ({:.2}, {:.2}, {:.2})\n\
+         speed: {:.2}\n\
+         heading: ({:.2}, {:.2}, {:.2})\n\
+         roll: {:.1}, pitch: {:.1}, yaw: {:.1}\n\
+         objects: {}",
+        fps,
+        camera.position.x,
+        camera.position.y,
+        camera.position.z,
+        speed,
+        heading.x,
+        heading.y,
+        heading.z,
+        roll.to_degrees(),
+        pitch.to_degrees(),
+        yaw.to_degrees(),
+        objects.len(),
+    );
+
+    if let Some(i) = selected.filter(|&i| i < objects.len()) {
+        let obj = &objects[i];
+        info += &format!(
+            "\nselected: {}\n\
+             selected position: ({:.2}, {:.2}, {:.2})\n\
+             selected speed: {:.2}",
+            i,
+            obj.pose.pos.x,
+            obj.pose.pos.y,
+            obj.pose.pos.z,
+            obj.velocity.norm(),
+        );
+    }
+    if let Some(status) = status {
+        info += &format!("\n{}", status);
+    }
+    info
+}
+
+/// Bounding squares and border widths of the HUD rings, with the borders
+/// inside the squares. The angular projection gets rings 90 and 180 degrees
+/// from straight ahead, and the perspective projection a ring at the edge of
+/// its field of view.
+fn hud_rings(camera: &render::Camera) -> Vec<([f64; 4], f64)> {
+    use std::f64::consts::PI;
+
+    // both projections put a point `angle` off center `scale * angle` from the middle
+    let ring = |angle: f64, border: f64| {
+        let radius = camera.scale * angle - border;
+        (
+            graphics::rectangle::square(-radius, -radius, 2.0 * radius),
+            border,
+        )
+    };
+    match camera.projection {
+        render::ProjectionMode::Angular => vec![ring(0.5 * PI, 0.5), ring(PI, 1.0)],
+        render::ProjectionMode::Perspective { fov } => vec![ring(0.5 * fov, 1.0)],
+    }
+}
+
+/// A camera looking at the center of an object from the same direction as
+/// `camera`, backed off so the object fits within `FRAME_RADIUS` pixels of the
+/// middle of the screen at `camera`'s scale.
+fn frame_object(camera: &render::Camera, obj: &GameObject) -> render::Camera {
+    let center = obj.mesh.center().unwrap_or_else(R3::zero);
+    let center = obj.pose.orientation.rotate(&center) + obj.pose.pos;
+    let radius = obj
+        .mesh
+        .aabb()
+        .map_or(0.0, |(min, max)| 0.5 * (max - min).norm());
+
+    // the angle off center that lands `FRAME_RADIUS` pixels from the middle
+    let angle = match camera.projection {
+        render::ProjectionMode::Angular => FRAME_RADIUS / camera.scale,
+        render::ProjectionMode::Perspective { fov } => {
+            let half_fov = 0.5 * fov;
+            let focal_length = camera.scale * half_fov / half_fov.tan();
+            (FRAME_RADIUS / focal_length).atan()
+        }
+    };
+    // past 90 degrees the object can't get any bigger on screen
+    let distance = radius / angle.min(0.5 * std::f64::consts::PI).sin();
+
+    let mut forward = center - camera.position;
+    if forward.norm() < 1e-9 {
+        forward = camera.orientation.rotate(&R3::FORWARD);
+    }
+    let forward = forward.normalized();
+    let up = camera.orientation.rotate(&-R3::UP);
+
+    render::Camera {
+        position: center - forward * distance,
+        orientation: Quaternion::look_at(forward, up),
+        ..*camera
+    }
+}
+
+/// A file name made of a prefix and the current time in seconds
+fn timestamped(prefix: &str, extension: &str) -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    format!("{}-{}.{}", prefix, seconds, extension)
+}
+
+/// Save what was just drawn to a png in the working directory named after the
+/// current time. The size is the viewport's size in pixels.
+#[cfg(feature = "image")]
+fn save_screenshot([width, height]: [u32; 2]) {
+    let path = timestamped("screenshot", "png");
+    match render::capture_screenshot(width, height).save(&path) {
+        Ok(()) => println!("Saved {}", path),
+        Err(e) => println!("Could not save {}: {}", path, e),
+    }
+}
+
+#[cfg(not(feature = "image"))]
+fn save_screenshot(_size: [u32; 2]) {
+    println!("Screenshots need the image feature");
+}
+
+/// Limit a rotation, given as its axis scaled by its angle or rate, to at most
+/// `max`. Tiny rotations are dropped entirely so they don't cause slow drift.
+fn limit_rotation(rotation: R3, max: f64) -> R3 {
+    const DEAD_ZONE: f64 = 1e-6;
+
+    if rotation.norm() < DEAD_ZONE {
+        R3::zero()
+    } else {
+        rotation.clamp_length(max)
+    }
+}
+
+/// Move `value` exponentially towards `target` at `rate` per second, landing
+/// exactly on it once it is close enough.
+fn approach(value: f64, target: f64, rate: f64, dt: f64) -> f64 {
+    const EPSILON: f64 = 1e-3;
+
+    let next = target + damp(value - target, rate, dt);
+    if (next - target).abs() < EPSILON {
+        target
+    } else {
+        next
+    }
+}
+
+/// Exponentially decay `value` towards zero at `rate` per second over `dt` seconds.
+/// It halves every `ln(2) / rate` seconds, regardless of how that time is split into steps.
+fn damp(value: f64, rate: f64, dt: f64) -> f64 {
+    value * (-rate * dt).exp()
+}
 
 fn main() {
     // Change this to OpenGL::V2_1 if not working.
     let opengl = OpenGL::V4_5;
 
-    // Create a Glutin window.
-    let mut window: Window = WindowSettings::new("spinning-square", [800, 600])
+    let args: Vec<String> = std::env::args().collect();
+
+    // Create a Glutin window, filling the screen unless `--windowed` is given.
+    let mut window: Window = WindowSettings::new("spinning-square", [1280, 720])
         .graphics_api(opengl)
         .exit_on_esc(true)
-        .fullscreen(true)
+        .fullscreen(!args.iter().any(|arg| arg == "--windowed"))
+        .resizable(true)
         .vsync(true)
         // .samples(4)
         .build()
@@ -533,23 +1303,571 @@ fn main() {
     // init the opengl function pointers
     gl::load_with(|s| window.get_proc_address(s) as *const _);
 
+    // pick which built in objects to start with as `--demo <name>`
+    let demo = args
+        .iter()
+        .position(|arg| arg == "--demo")
+        .map_or("octahedron", |i| {
+            args.get(i + 1).expect("--demo needs a name")
+        });
+
+    let mut gl = GlGraphics::new(opengl);
+    let mut glyph_cache = GlyphCache::new(
+        resource_path("OpenSans-Regular.ttf"),
+        (),
+        TextureSettings::new(),
+    )
+    .unwrap();
+
     let mut app = initial_app(
-        GlGraphics::new(opengl),
+        piston::window::Window::size(&window).height,
         1.0,
         40.0,
         0.0,
+        demo,
         // SystemTime::now(),
         // 10,
     );
 
+    // replace the built in objects with a scene file given as `--scene <path>`
+    // once its meshes have loaded
+    if let Some(i) = args.iter().position(|arg| arg == "--scene") {
+        let path = args.get(i + 1).expect("--scene needs a path");
+        match scene::load_scene(&resource_path(path).to_string_lossy()) {
+            Ok(loading) => {
+                if let Some(camera) = loading.camera {
+                    app.camera = camera;
+                    app.initial_camera = camera;
+                    app.target_scale = camera.scale;
+                }
+                app.loading = Some(loading);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                app.load_error = Some(e);
+            }
+        }
+    }
+
+    // remap controls from a json file given as `--keys <path>`
+    if let Some(i) = args.iter().position(|arg| arg == "--keys") {
+        let path = args.get(i + 1).expect("--keys needs a path");
+        app.keys = controls::KeyBindings::load(&resource_path(path).to_string_lossy())
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    // save every input to a file given as `--record <path>` when the window closes
+    let record_path = args.iter().position(|arg| arg == "--record").map(|i| {
+        app.recorder = Some(replay::Recorder::new());
+        args.get(i + 1).expect("--record needs a path").clone()
+    });
+
+    // play back inputs from a file given as `--replay <path>` instead of live
+    // input, then hand control back once it runs out
+    let mut replay = args.iter().position(|arg| arg == "--replay").map(|i| {
+        let path = args.get(i + 1).expect("--replay needs a path");
+        replay::Replay::load(path).unwrap_or_else(|e| panic!("{}", e))
+    });
+
     let mut events = Events::new(EventSettings::new().max_fps(60).ups(60));
     while let Some(e) = events.next(&mut window) {
+        if let Some(playing) = &mut replay {
+            match e {
+                Event::Loop(Loop::Render(args)) => app.render(args, &mut gl, &mut glyph_cache),
+                Event::Loop(Loop::Update(_)) => {
+                    for event in playing.next_frame() {
+                        app.input(event);
+                    }
+                    if playing.is_finished() {
+                        println!("Replay finished");
+                        replay = None;
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
         match e {
-            Event::Loop(Loop::Render(args)) => app.render(args),
-            Event::Loop(Loop::Update(args)) => app.update(args),
-            Event::Input(Input::Button(args), _) => app.button(args),
-            Event::Input(Input::Move(args), _) => app.mouse(args),
+            Event::Loop(Loop::Render(args)) => app.render(args, &mut gl, &mut glyph_cache),
+            Event::Loop(Loop::Update(args)) => app.input(replay::InputEvent::Update(args)),
+            Event::Input(Input::Button(args), _) => app.input(replay::InputEvent::Button(args)),
+            Event::Input(Input::Move(args), _) => app.input(replay::InputEvent::Motion(args)),
             _ => {}
         }
     }
+
+    if let (Some(recorder), Some(path)) = (&app.recorder, record_path) {
+        match recorder.save(&path) {
+            Ok(()) => println!("Saved {}", path),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A still object with `mesh` at `pos`
+    pub fn object(mesh: mesh::Mesh, pos: R3) -> GameObject {
+        GameObject {
+            bounding_radius: mesh.bounding_radius(),
+            bounding_sphere: mesh.bounding_sphere(),
+            mesh,
+            spec: None,
+            pose: pose::Pose {
+                pos,
+                orientation: Quaternion::zero_rotation(),
+            },
+            world_vertices: None,
+            visible: true,
+            physics_enabled: true,
+            integrator: Integrator::Euler,
+            velocity: R3::zero(),
+            acceleration: R3::zero(),
+            angular_velocity: R3::zero(),
+            angular_acceleration: R3::zero(),
+        }
+    }
+
+    /// Largest change in energy of a unit mass on a unit spring over `steps`
+    fn spring_energy_drift(integrator: Integrator, dt: f64, steps: usize) -> f64 {
+        let energy = |pos: R3, velocity: R3| 0.5 * (dot(&pos, &pos) + dot(&velocity, &velocity));
+        let (mut pos, mut velocity) = (R3::new(1.0, 0.0, 0.0), R3::new(0.0, 0.5, 0.0));
+        let start = energy(pos, velocity);
+
+        let mut drift: f64 = 0.0;
+        for _ in 0..steps {
+            let next = integrator.step(pos, velocity, dt, |p, _| -p);
+            pos = next.0;
+            velocity = next.1;
+            drift = drift.max((energy(pos, velocity) - start).abs());
+        }
+        drift
+    }
+
+    #[test]
+    fn rk4_conserves_spring_energy_better_than_euler() {
+        let euler = spring_energy_drift(Integrator::Euler, 0.01, 10_000);
+        let rk4 = spring_energy_drift(Integrator::Rk4, 0.01, 10_000);
+        assert!(rk4 < 1e-6, "rk4 drifted by {}", rk4);
+        assert!(rk4 * 1000.0 < euler, "rk4 {} vs euler {}", rk4, euler);
+    }
+
+    #[test]
+    fn drag_slows_objects_towards_zero() {
+        for integrator in [Integrator::Euler, Integrator::Rk4] {
+            let mut velocity = R3::new(3.0, 4.0, 0.0);
+            let mut speed = velocity.norm();
+            for _ in 0..1000 {
+                velocity = integrator
+                    .step(R3::zero(), velocity, PHYSICS_DT, |_, v| -v * 0.5)
+                    .1;
+                assert!(velocity.norm() < speed);
+                speed = velocity.norm();
+            }
+        }
+    }
+
+    #[test]
+    fn rk4_drag_matches_exponential_decay() {
+        let (drag, dt) = (2.0, 0.1);
+        let (_, velocity) =
+            Integrator::Rk4.step(R3::zero(), R3::new(1.0, 0.0, 0.0), dt, |_, v| -v * drag);
+        let exact = (-drag * dt).exp();
+        assert!(
+            (velocity.x - exact).abs() < 1e-5,
+            "{} vs {}",
+            velocity.x,
+            exact
+        );
+    }
+
+    #[test]
+    fn rk4_is_exact_for_constant_acceleration() {
+        let (pos, velocity) = (R3::new(1.0, 2.0, 3.0), R3::new(-1.0, 0.5, 0.0));
+        let acceleration = R3::new(0.0, 0.0, -9.8);
+        let dt = 0.5;
+
+        let (p, v) = Integrator::Rk4.step(pos, velocity, dt, |_, _| acceleration);
+        assert!(p.approx_eq(
+            &(pos + velocity * dt + acceleration * (0.5 * dt * dt)),
+            1e-12
+        ));
+        assert!(v.approx_eq(&(velocity + acceleration * dt), 1e-12));
+    }
+
+    #[test]
+    fn objects_are_culled_by_where_their_mesh_is() {
+        let camera = render::Camera {
+            position: R3::zero(),
+            orientation: Quaternion::zero_rotation(),
+            scale: 500.0,
+            projection: render::ProjectionMode::Perspective { fov: 1.2 },
+        };
+        let size = [800.0, 600.0];
+        let cube = |offset| mesh::translate(mesh::cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]), offset);
+
+        // far off to the side of the camera
+        let beside = object(cube(R3::zero()), R3::new(0.0, 50.0, 0.0));
+        assert!(!beside.should_draw(&camera, size));
+
+        // the pose is off screen, but the mesh is moved back in front of the camera
+        let offset = object(cube(R3::new(20.0, -50.0, 0.0)), R3::new(0.0, 50.0, 0.0));
+        assert!(offset.should_draw(&camera, size));
+
+        let hidden = GameObject {
+            visible: false,
+            ..object(cube(R3::zero()), R3::new(20.0, 0.0, 0.0))
+        };
+        assert!(!hidden.should_draw(&camera, size));
+    }
+
+    #[test]
+    fn frame_history_keeps_the_most_recent_times() {
+        let mut frame_times = VecDeque::new();
+        for i in 0..FRAME_HISTORY + 5 {
+            push_frame_time(&mut frame_times, i as f64);
+        }
+
+        let expected: Vec<f64> = (5..FRAME_HISTORY + 5).map(|i| i as f64).collect();
+        assert_eq!(frame_times.iter().copied().collect::<Vec<_>>(), expected);
+    }
+
+    /// The default octahedron demo in a 1280x720 window
+    fn demo_app() -> App {
+        let mut app = initial_app(720.0, 1.0, 40.0, 0.0, "octahedron");
+        app.window_size = [1280.0, 720.0];
+        app
+    }
+
+    /// Click the left mouse button at a point in the window
+    fn click(app: &mut App, cursor: [f64; 2]) {
+        app.input(replay::InputEvent::Motion(Motion::MouseCursor(cursor)));
+        app.input(replay::InputEvent::Button(ButtonArgs {
+            state: ButtonState::Press,
+            button: Button::Mouse(MouseButton::Left),
+            scancode: None,
+        }));
+    }
+
+    #[test]
+    fn clicking_an_object_selects_it() {
+        let mut app = demo_app();
+        // the camera starts looking at the octahedron
+        click(&mut app, [640.0, 360.0]);
+        assert_eq!(app.selected, Some(0));
+        click(&mut app, [660.0, 370.0]);
+        assert_eq!(app.selected, Some(0));
+
+        // empty space clears the selection
+        click(&mut app, [640.0, 100.0]);
+        assert_eq!(app.selected, None);
+    }
+
+    /// Press and release a key
+    fn press(app: &mut App, key: Key) {
+        for state in [ButtonState::Press, ButtonState::Release] {
+            app.input(replay::InputEvent::Button(ButtonArgs {
+                state,
+                button: Button::Keyboard(key),
+                scancode: None,
+            }));
+        }
+    }
+
+    fn update(app: &mut App, dt: f64) {
+        app.input(replay::InputEvent::Update(UpdateArgs { dt }));
+    }
+
+    #[test]
+    fn recalling_a_viewpoint_restores_the_stored_camera() {
+        let mut app = demo_app();
+        let keys = controls::KeyBindings::default();
+        let stored = app.camera;
+        press(&mut app, keys.store_viewpoint[1]);
+
+        app.camera.position += R3::new(5.0, -2.0, 1.0);
+        app.camera.orientation = Quaternion::rotation(R3::new(0.0, 1.0, 0.0), 0.3);
+        app.velocity = 10.0;
+        app.recall_viewpoint(1, false);
+        assert_eq!(app.camera, stored);
+        assert_eq!(app.velocity, 0.0);
+
+        // the recall key flies there instead
+        app.camera.position += R3::new(5.0, -2.0, 1.0);
+        press(&mut app, keys.recall_viewpoint[1]);
+        assert_ne!(app.camera, stored);
+        update(&mut app, VIEWPOINT_TRANSITION + 0.1);
+        assert_eq!(app.camera, stored);
+
+        // empty slots leave the camera alone
+        app.camera.position += R3::new(5.0, -2.0, 1.0);
+        let moved = app.camera;
+        app.recall_viewpoint(2, false);
+        assert_eq!(app.camera, moved);
+    }
+
+    #[test]
+    fn orbiting_keeps_the_distance_to_the_target() {
+        let mut app = demo_app();
+        let distance = app.camera.position.distance(&app.orbit_target);
+        app.input(replay::InputEvent::Button(ButtonArgs {
+            state: ButtonState::Press,
+            button: Button::Mouse(MouseButton::Right),
+            scancode: None,
+        }));
+
+        let start = app.camera.position;
+        for mov in [[5.0, 0.0], [0.0, -3.0], [-8.0, 6.0]] {
+            app.input(replay::InputEvent::Motion(Motion::MouseRelative(mov)));
+            update(&mut app, 1.0 / 60.0);
+
+            let to_target = app.orbit_target - app.camera.position;
+            assert!((to_target.norm() - distance).abs() < 1e-9);
+            // still looking at the target
+            let forward = app.camera.orientation.rotate(&R3::FORWARD);
+            assert!(forward.approx_eq(&to_target.normalized(), 1e-9));
+        }
+        assert!(app.camera.position.distance(&start) > 1.0);
+    }
+
+    #[test]
+    fn damping_halves_in_the_half_life_however_it_is_stepped() {
+        let rate = 0.5;
+        let half_life = 2f64.ln() / rate;
+        assert!((damp(8.0, rate, half_life) - 4.0).abs() < 1e-12);
+
+        let stepped = (0..100).fold(8.0, |v, _| damp(v, rate, half_life / 100.0));
+        assert!((stepped - 4.0).abs() < 1e-12);
+        assert_eq!(damp(8.0, rate, 0.0), 8.0);
+    }
+
+    #[test]
+    fn rotations_are_clamped_to_the_max_rate() {
+        let fast = limit_rotation(R3::new(3.0, 4.0, 0.0), 1.0);
+        assert!((fast.norm() - 1.0).abs() < 1e-12);
+        // the axis is kept
+        assert!(fast.approx_eq(&R3::new(0.6, 0.8, 0.0), 1e-12));
+
+        let slow = R3::new(0.1, 0.0, -0.2);
+        assert_eq!(limit_rotation(slow, 1.0), slow);
+        assert_eq!(limit_rotation(R3::new(1e-7, 0.0, 0.0), 1.0), R3::zero());
+    }
+
+    #[test]
+    fn zooming_lands_on_the_target_without_overshooting() {
+        for (start, target) in [(100.0, 400.0), (400.0, 100.0)] {
+            let mut scale = start;
+            let mut steps = 0;
+            while scale != target {
+                let next = approach(scale, target, ZOOM_RATE, 1.0 / 60.0);
+                // always closer, and never past it
+                assert!((next - target).abs() < (scale - target).abs());
+                assert!((next - target) * (start - target) >= 0.0);
+                scale = next;
+                steps += 1;
+                assert!(steps < 1000, "zooming should finish");
+            }
+        }
+    }
+
+    #[test]
+    fn hud_rings_fit_the_window() {
+        // the starting camera's scale comes from the 720 pixel window height
+        let app = demo_app();
+        let outer_edge = |(rect, border): ([f64; 4], f64)| {
+            // centered on the middle of the screen
+            assert_eq!(rect[0], -0.5 * rect[2]);
+            assert_eq!(rect[2], rect[3]);
+            0.5 * rect[2] + border
+        };
+
+        let rings = hud_rings(&app.camera);
+        assert_eq!(rings.len(), 2);
+        // 90 degrees is halfway out, and 180 degrees reaches the top and bottom
+        assert!((outer_edge(rings[0]) - 180.0).abs() < 1e-9);
+        assert!((outer_edge(rings[1]) - 360.0).abs() < 1e-9);
+
+        let perspective = render::Camera {
+            scale: 500.0,
+            projection: render::ProjectionMode::Perspective { fov: 1.2 },
+            ..app.camera
+        };
+        let rings = hud_rings(&perspective);
+        assert_eq!(rings.len(), 1);
+        assert!((outer_edge(rings[0]) - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn world_vertices_are_cached_until_the_pose_changes() {
+        let mut obj = object(
+            mesh::cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]),
+            R3::new(5.0, 0.0, 0.0),
+        );
+        obj.cache_world_vertices();
+        let cached = obj.world_vertices.as_ref().unwrap().as_ptr();
+
+        // staying still keeps the same vertices around
+        obj.physics_step(0.1, R3::zero(), 0.0);
+        obj.cache_world_vertices();
+        assert_eq!(obj.world_vertices.as_ref().unwrap().as_ptr(), cached);
+
+        obj.velocity = R3::new(1.0, 0.0, 0.0);
+        obj.physics_step(0.1, R3::zero(), 0.0);
+        assert!(obj.world_vertices.is_none());
+        obj.cache_world_vertices();
+        assert_eq!(
+            obj.world_vertices,
+            Some(mesh::world_vertices(&obj.mesh, &obj.pose))
+        );
+    }
+
+    /// The demo app with its objects replaced by still 2x2x2 cubes at `positions`
+    fn app_with_cubes(positions: &[R3]) -> App {
+        let mut app = demo_app();
+        app.objects = positions
+            .iter()
+            .map(|&pos| object(mesh::cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]), pos))
+            .collect();
+        app
+    }
+
+    #[test]
+    fn only_objects_with_physics_move() {
+        let mut app = app_with_cubes(&[R3::zero(), R3::new(0.0, 10.0, 0.0)]);
+        app.objects[1].physics_enabled = false;
+        // hidden objects still move
+        app.objects[0].visible = false;
+        for obj in app.objects.iter_mut() {
+            obj.velocity = R3::new(1.0, 0.0, 0.0);
+        }
+
+        update(&mut app, 10.0 * PHYSICS_DT);
+        assert!(app.objects[0].pose.pos.x > 0.0);
+        assert_eq!(app.objects[1].pose.pos, R3::new(0.0, 10.0, 0.0));
+    }
+
+    #[test]
+    fn paused_objects_only_move_when_stepped() {
+        let mut app = app_with_cubes(&[R3::zero()]);
+        app.objects[0].velocity = R3::new(1.0, 0.0, 0.0);
+        let keys = controls::KeyBindings::default();
+
+        press(&mut app, keys.toggle_pause);
+        update(&mut app, 10.0 * PHYSICS_DT);
+        assert_eq!(app.objects[0].pose.pos, R3::zero());
+
+        press(&mut app, keys.step_once);
+        assert!((app.objects[0].pose.pos.x - PHYSICS_DT).abs() < 1e-12);
+
+        // stepping does nothing while running
+        press(&mut app, keys.toggle_pause);
+        press(&mut app, keys.step_once);
+        assert!((app.objects[0].pose.pos.x - PHYSICS_DT).abs() < 1e-12);
+    }
+
+    #[test]
+    fn updates_run_whole_physics_steps_and_keep_the_rest() {
+        let mut app = app_with_cubes(&[R3::zero()]);
+        app.objects[0].velocity = R3::new(1.0, 0.0, 0.0);
+
+        update(&mut app, 2.5 * PHYSICS_DT);
+        assert!((app.objects[0].pose.pos.x - 2.0 * PHYSICS_DT).abs() < 1e-12);
+        assert!((app.physics_accumulator - 0.5 * PHYSICS_DT).abs() < 1e-12);
+
+        // the leftover half step makes up a whole one with the next update
+        update(&mut app, 0.75 * PHYSICS_DT);
+        assert!((app.objects[0].pose.pos.x - 3.0 * PHYSICS_DT).abs() < 1e-12);
+        assert!((app.physics_accumulator - 0.25 * PHYSICS_DT).abs() < 1e-12);
+    }
+
+    #[test]
+    fn only_overlapping_boxes_reach_the_collision_check() {
+        // the bounding spheres of the first two overlap, but not their boxes
+        let mut app = app_with_cubes(&[
+            R3::zero(),
+            R3::new(2.2, 2.2, 0.0),
+            R3::new(-1.5, 0.0, 0.0),
+            R3::new(0.0, 0.0, 1.5),
+        ]);
+        assert!(collision::collide(&app.objects[0], &app.objects[1]));
+        app.objects[3].physics_enabled = false;
+        assert_eq!(app.collisions(), vec![(0, 2)]);
+
+        // so moving towards each other they don't bounce
+        app.objects[0].velocity = R3::new(1.0, 1.0, 0.0);
+        app.objects[1].velocity = R3::new(-1.0, -1.0, 0.0);
+        app.step_objects(PHYSICS_DT);
+        assert_eq!(app.objects[0].velocity, R3::new(1.0, 1.0, 0.0));
+        assert_eq!(app.objects[1].velocity, R3::new(-1.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn framing_puts_the_object_in_the_middle_of_the_screen() {
+        let obj = object(
+            mesh::cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]),
+            R3::new(50.0, 10.0, 0.0),
+        );
+        let radius = 3f64.sqrt();
+
+        for projection in [
+            render::ProjectionMode::Angular,
+            render::ProjectionMode::Perspective { fov: 1.2 },
+        ] {
+            let camera = render::Camera {
+                position: R3::zero(),
+                orientation: Quaternion::zero_rotation(),
+                scale: 300.0,
+                projection,
+            };
+            let framed = frame_object(&camera, &obj);
+            assert_eq!(
+                (framed.scale, framed.projection),
+                (camera.scale, camera.projection)
+            );
+
+            let [x, y] = render::to_screen_space(&obj.pose.pos, &framed);
+            assert!(x.abs() < 1e-3 && y.abs() < 1e-3);
+            // the edge of the box's sphere lands `FRAME_RADIUS` from the middle
+            let right = framed.orientation.rotate(&R3::RIGHT);
+            let distance = framed.position.distance(&obj.pose.pos);
+            let edge = obj.pose.pos
+                + right * (radius * distance / (distance.powi(2) - radius.powi(2)).sqrt());
+            let [x, _] = render::to_screen_space(&edge, &framed);
+            assert!((x - FRAME_RADIUS).abs() < 1e-3);
+            // without rolling the camera over
+            let up = camera.orientation.rotate(&-R3::UP);
+            assert!(dot(&framed.orientation.rotate(&-R3::UP), &up) > 0.0);
+        }
+    }
+
+    #[test]
+    fn hud_shows_the_selected_object() {
+        let app = app_with_cubes(&[R3::zero(), R3::new(1.0, -2.5, 3.0)]);
+        let mut objects = app.objects;
+        objects[1].velocity = R3::new(3.0, 4.0, 0.0);
+
+        let info = hud_info(60.0, &app.camera, 2.0, &objects, Some(1), None);
+        let lines: Vec<&str> = info.lines().collect();
+        assert_eq!(lines[0], "FPS: 60.00");
+        assert_eq!(lines[2], "speed: 2.00");
+        assert!(lines.contains(&"objects: 2"));
+        assert!(lines.contains(&"selected: 1"));
+        assert!(lines.contains(&"selected position: (1.00, -2.50, 3.00)"));
+        assert_eq!(lines.last(), Some(&"selected speed: 5.00"));
+
+        // nothing about a selection that's gone, but the status goes last
+        let info = hud_info(
+            60.0,
+            &app.camera,
+            2.0,
+            &objects[..1],
+            Some(1),
+            Some("loading"),
+        );
+        assert!(!info.contains("selected"));
+        assert!(info.ends_with("\nloading"));
+    }
 }
@@ -1,48 +1,231 @@
-use graphics;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use wavefront_obj::obj;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use wavefront_obj::{mtl, obj};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
 
 use graphics::types::Color;
 
-use super::r3::{cross, dot, pose::Pose, R3};
+use super::r3::{cross, dot, hadamard, midpoint, pose::Pose, R3};
 use super::render::*;
+use super::resource::resource_path;
+
+/// Vertices closer together than this are treated as the same vertex
+const WELD_TOLERANCE: f64 = 1e-6;
+
+/// How opaque faces are compared to the lines around them
+const FACE_ALPHA: f32 = 0.25;
+
+/// The color to fill faces of a mesh drawn in `color`
+fn face_color(color: Color) -> Color {
+    [color[0], color[1], color[2], FACE_ALPHA * color[3]]
+}
+
+/// The index of a vertex within `WELD_TOLERANCE` of `v`, adding `v` if there isn't one.
+/// `buckets` groups the vertices into cells the size of the tolerance, so only
+/// the neighboring cells need to be searched.
+fn weld(
+    vertices: &mut Vec<R3>,
+    buckets: &mut HashMap<(i64, i64, i64), Vec<usize>>,
+    v: R3,
+) -> usize {
+    let cell = |x: f64| (x / WELD_TOLERANCE).floor() as i64;
+    let (x, y, z) = (cell(v.x), cell(v.y), cell(v.z));
+
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                if let Some(bucket) = buckets.get(&(x + dx, y + dy, z + dz)) {
+                    if let Some(&index) = bucket
+                        .iter()
+                        .find(|&&i| vertices[i].approx_eq(&v, WELD_TOLERANCE))
+                    {
+                        return index;
+                    }
+                }
+            }
+        }
+    }
+
+    let index = vertices.len();
+    vertices.push(v);
+    buckets.entry((x, y, z)).or_default().push(index);
+    index
+}
 
+/// Merge duplicate vertices, edges, lines, and faces. Faces that collapse to
+/// a line or a point are dropped.
 pub fn condense_mesh(mesh: &Mesh) -> Mesh {
-    let mut mapping = Vec::new();
+    let mut buckets = HashMap::new();
     let mut vertices = Vec::new();
+    let vertex_mapping: Vec<usize> = mesh
+        .vertices
+        .iter()
+        .map(|&v| weld(&mut vertices, &mut buckets, v))
+        .collect();
 
-    for &v in &mesh.vertices {
-        match vertices.iter().position(|&nv| nv == v) {
-            Some(index) => {
-                mapping.push(index);
+    // old edge index -> new edge index and whether it was flipped, or None if it collapsed
+    let mut edge_map = HashMap::new();
+    let mut edges = Vec::new();
+    let edge_mapping: Vec<Option<(usize, bool)>> = mesh
+        .edges
+        .iter()
+        .map(|&(a, b)| {
+            let (a, b) = (vertex_mapping[a], vertex_mapping[b]);
+            if a == b {
+                None
+            } else {
+                Some((get_edge(&mut edges, &mut edge_map, a, b), b < a))
             }
+        })
+        .collect();
 
-            None => {
-                mapping.push(vertices.len());
-                vertices.push(v);
+    let mut line_set = HashSet::new();
+    let lines = mesh
+        .lines
+        .iter()
+        .filter_map(|&(edge, color)| edge_mapping[edge].map(|(edge, _)| (edge, color)))
+        .filter(|&(edge, _)| line_set.insert(edge))
+        .collect();
+
+    // rewrite a face in terms of the new edges, or None if it is degenerate
+    let remap_face = |face: &[(usize, bool)]| -> Option<Vec<(usize, bool)>> {
+        let remapped: Option<Vec<_>> = face
+            .iter()
+            .map(|&(edge, reversed)| {
+                edge_mapping[edge].map(|(e, flipped)| (e, reversed != flipped))
+            })
+            .collect();
+        remapped.filter(|edges| {
+            let mut sorted: Vec<usize> = edges.iter().map(|&(e, _)| e).collect();
+            sorted.sort_unstable();
+            sorted.dedup();
+            sorted.len() == face.len()
+        })
+    };
+    let face_key = |face: &[(usize, bool)]| {
+        let mut key: Vec<usize> = face.iter().map(|&(e, _)| e).collect();
+        key.sort_unstable();
+        key
+    };
+
+    let mut face_set = HashSet::new();
+    let mut triangles = Vec::new();
+    let mut triangle_normals = Vec::new();
+    for (index, (face, color)) in mesh.triangles.iter().enumerate() {
+        if let Some(edges) = remap_face(face) {
+            if face_set.insert(face_key(&edges)) {
+                triangles.push(([edges[0], edges[1], edges[2]], *color));
+                if let Some(&normals) = mesh.triangle_normals.get(index) {
+                    triangle_normals.push(normals);
+                }
+            }
+        }
+    }
+
+    let mut parallelograms = Vec::new();
+    let mut parallelogram_normals = Vec::new();
+    for (index, (face, color)) in mesh.parallelograms.iter().enumerate() {
+        if let Some(edges) = remap_face(face) {
+            if face_set.insert(face_key(&edges)) {
+                parallelograms.push(([edges[0], edges[1], edges[2], edges[3]], *color));
+                if let Some(&normals) = mesh.parallelogram_normals.get(index) {
+                    parallelogram_normals.push(normals);
+                }
             }
         }
     }
 
     Mesh {
         vertices,
-        edges: mesh
-            .edges
-            .iter()
-            .map(|&(a, b)| (mapping[a], mapping[b]))
-            .collect(),
+        edges,
+        lines,
+        triangles,
+        parallelograms,
+        normals: mesh.normals.clone(),
+        triangle_normals,
+        parallelogram_normals,
+    }
+}
+
+/// Checks if the quad a, b, c, d is a parallelogram, ie its diagonals bisect each other
+fn is_parallelogram(vertices: &[R3], a: usize, b: usize, c: usize, d: usize) -> bool {
+    const EPSILON: f64 = 1e-9;
+    (vertices[a] + vertices[c]).approx_eq(&(vertices[b] + vertices[d]), EPSILON)
+}
 
-        lines: mesh.lines.clone(),
-        triangles: mesh.triangles.clone(),
-        parallelograms: mesh.parallelograms.clone(),
+/// Errors from loading a mesh, along with the path of the offending file
+#[derive(Debug)]
+pub enum MeshError {
+    Io(io::Error, String),
+    Parse(String, String),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::Io(e, path) => write!(f, "Could not read {}: {}", path, e),
+            MeshError::Parse(message, path) => write!(f, "Could not parse {}: {}", path, message),
+        }
     }
 }
 
-pub fn mk_meshes(path: &str, color: Color) -> Result<Mesh, String> {
-    let file = fs::read_to_string(path).map_err(|_| "Could not read file")?;
+impl std::error::Error for MeshError {}
 
-    let obj_set = obj::parse(file).map_err(|e| e.message)?;
+fn read_file(path: &Path) -> Result<String, MeshError> {
+    fs::read_to_string(path).map_err(|e| MeshError::Io(e, path.display().to_string()))
+}
+
+/// Load the diffuse colors from a material library, keyed by material name
+fn load_materials(path: &Path) -> Result<HashMap<String, Color>, MeshError> {
+    let file = read_file(path)?;
+
+    let mtl_set =
+        mtl::parse(file).map_err(|e| MeshError::Parse(e.message, path.display().to_string()))?;
+
+    Ok(mtl_set
+        .materials
+        .iter()
+        .map(|m| {
+            let kd = m.color_diffuse;
+            let color = [kd.r as f32, kd.g as f32, kd.b as f32, m.alpha as f32];
+            (m.name.clone(), color)
+        })
+        .collect())
+}
+
+/// Load a mesh from an obj file.
+///
+/// Groups that use a material from the obj's material library are colored
+/// with its diffuse color, everything else gets `color`.
+pub fn mk_meshes(path: &str, color: Color) -> Result<Mesh, MeshError> {
+    let file = read_file(Path::new(path))?;
+
+    let obj_set = obj::parse(file).map_err(|e| MeshError::Parse(e.message, path.to_string()))?;
+
+    let materials = match &obj_set.material_library {
+        Some(library) => {
+            let library_path = Path::new(path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(library);
+            load_materials(&library_path).unwrap_or_else(|e| {
+                println!("Ignoring materials: {}", e);
+                HashMap::new()
+            })
+        }
+
+        None => HashMap::new(),
+    };
 
     let mut vertices = Vec::new();
     let mut vertex_offset;
@@ -50,39 +233,57 @@ pub fn mk_meshes(path: &str, color: Color) -> Result<Mesh, String> {
     let mut edge_map = HashMap::new();
     let mut edges = Vec::new();
 
-    fn get_edge(
-        edges: &mut Vec<(usize, usize)>,
-        edge_map: &mut HashMap<(usize, usize), usize>,
-        a: usize,
-        b: usize,
-    ) -> usize {
-        if b < a {
-            get_edge(edges, edge_map, b, a)
-        } else {
-            // add this edge to the list if its not already there
-            match edge_map.get(&(a, b)) {
-                Some(&index) => index,
-
-                None => {
-                    let index = edges.len();
-                    edges.push((a, b));
-                    edge_map.insert((a, b), index);
-                    index
-                }
-            }
-        }
+    /// The normal index for each corner of a face. Corners without a normal
+    /// share a geometric normal computed from the first three vertices.
+    fn corner_normals(
+        normals: &mut Vec<R3>,
+        vertices: &[R3],
+        normal_offset: usize,
+        face: &[usize; 3],
+        corners: &[Option<usize>],
+    ) -> Vec<usize> {
+        let mut geometric = None;
+        corners
+            .iter()
+            .map(|corner| match corner {
+                Some(n) => n + normal_offset,
+                None => *geometric.get_or_insert_with(|| {
+                    let [a, b, c] = face.map(|i| vertices[i]);
+                    normals.push(cross(&(b - a), &(c - a)).normalized());
+                    normals.len() - 1
+                }),
+            })
+            .collect()
     }
 
     let mut lines = Vec::new();
     let mut triangles = Vec::new();
-    let face_color = [color[0], color[1], color[2], 0.125 * color[3]];
+    let mut parallelograms = Vec::new();
+
+    // normals are only tracked if the file has some
+    let has_normals = obj_set.objects.iter().any(|o| !o.normals.is_empty());
+    let mut normals = Vec::new();
+    let mut normal_offset;
+    let mut triangle_normals = Vec::new();
+    let mut parallelogram_normals = Vec::new();
 
     for object in &obj_set.objects {
         vertex_offset = vertices.len();
-        vertices.extend(object.vertices.iter().map(|v| R3::new(v.x, v.y, v.z)));
+        vertices.extend(object.vertices.iter().map(|v| R3::from((v.x, v.y, v.z))));
+        normal_offset = normals.len();
+        normals.extend(object.normals.iter().map(|n| R3::from((n.x, n.y, n.z))));
 
         for g in &object.geometry {
-            for shape in &g.shapes {
+            let color = g
+                .material_name
+                .as_ref()
+                .and_then(|name| materials.get(name))
+                .copied()
+                .unwrap_or(color);
+            let face_color = face_color(color);
+
+            let mut shapes = g.shapes.iter().peekable();
+            while let Some(shape) = shapes.next() {
                 match shape.primitive {
                     obj::Primitive::Point(p) => println!("Ignoring a point! {}", p.0),
 
@@ -92,25 +293,81 @@ pub fn mk_meshes(path: &str, color: Color) -> Result<Mesh, String> {
                         lines.push((get_edge(&mut edges, &mut edge_map, a, b), color));
                     }
 
-                    obj::Primitive::Triangle((obj_a, _, _an), (obj_b, _, _bn), (obj_c, _, _cn)) => {
+                    obj::Primitive::Triangle((obj_a, _, an), (obj_b, _, bn), (obj_c, _, cn)) => {
                         let a = obj_a + vertex_offset;
                         let b = obj_b + vertex_offset;
                         let c = obj_c + vertex_offset;
-                        // println!("T <{}, {}, {}>", a, b, c);
-                        let ab = get_edge(&mut edges, &mut edge_map, a, b);
-                        let bc = get_edge(&mut edges, &mut edge_map, b, c);
-                        let ca = get_edge(&mut edges, &mut edge_map, c, a);
-                        // lines.push((ab, color));
-                        // lines.push((bc, color));
-                        // lines.push((ca, color));
-                        triangles.push((
-                            [
-                                (ab, edges[ab].0 != a),
-                                (bc, edges[bc].0 != b),
-                                (ca, edges[ca].0 != c),
-                            ],
-                            face_color,
-                        ));
+
+                        // the parser splits quads into a fan of two triangles (a, b, c), (a, c, d)
+                        let quad_d = match shapes.peek().map(|s| s.primitive) {
+                            Some(obj::Primitive::Triangle(
+                                (obj_a2, _, _),
+                                (obj_c2, _, _),
+                                (obj_d, _, dn),
+                            )) if obj_a2 == obj_a && obj_c2 == obj_c => {
+                                Some((obj_d + vertex_offset, dn))
+                            }
+                            _ => None,
+                        };
+
+                        match quad_d {
+                            // keep the quad whole when it is a parallelogram, since that is
+                            // the only kind of four sided face the renderer knows about
+                            Some((d, dn)) if is_parallelogram(&vertices, a, b, c, d) => {
+                                shapes.next();
+                                if has_normals {
+                                    let ns = corner_normals(
+                                        &mut normals,
+                                        &vertices,
+                                        normal_offset,
+                                        &[a, b, c],
+                                        &[an, bn, cn, dn],
+                                    );
+                                    parallelogram_normals.push([ns[0], ns[1], ns[2], ns[3]]);
+                                }
+                                let ab = get_edge(&mut edges, &mut edge_map, a, b);
+                                let bc = get_edge(&mut edges, &mut edge_map, b, c);
+                                let cd = get_edge(&mut edges, &mut edge_map, c, d);
+                                let da = get_edge(&mut edges, &mut edge_map, d, a);
+                                parallelograms.push((
+                                    [
+                                        (ab, edges[ab].0 != a),
+                                        (bc, edges[bc].0 != b),
+                                        (cd, edges[cd].0 != c),
+                                        (da, edges[da].0 != d),
+                                    ],
+                                    face_color,
+                                ));
+                            }
+
+                            _ => {
+                                if has_normals {
+                                    let ns = corner_normals(
+                                        &mut normals,
+                                        &vertices,
+                                        normal_offset,
+                                        &[a, b, c],
+                                        &[an, bn, cn],
+                                    );
+                                    triangle_normals.push([ns[0], ns[1], ns[2]]);
+                                }
+                                // println!("T <{}, {}, {}>", a, b, c);
+                                let ab = get_edge(&mut edges, &mut edge_map, a, b);
+                                let bc = get_edge(&mut edges, &mut edge_map, b, c);
+                                let ca = get_edge(&mut edges, &mut edge_map, c, a);
+                                // lines.push((ab, color));
+                                // lines.push((bc, color));
+                                // lines.push((ca, color));
+                                triangles.push((
+                                    [
+                                        (ab, edges[ab].0 != a),
+                                        (bc, edges[bc].0 != b),
+                                        (ca, edges[ca].0 != c),
+                                    ],
+                                    face_color,
+                                ));
+                            }
+                        }
                     }
                 }
             }
@@ -122,17 +379,371 @@ pub fn mk_meshes(path: &str, color: Color) -> Result<Mesh, String> {
         edges,
         lines,
         triangles,
+        parallelograms,
+        normals,
+        triangle_normals,
+        parallelogram_normals,
+    })
+}
+
+/// The index of the edge between vertices a and b, adding it if it is new.
+/// Edges are stored with the smaller vertex index first.
+fn get_edge(
+    edges: &mut Vec<(usize, usize)>,
+    edge_map: &mut HashMap<(usize, usize), usize>,
+    a: usize,
+    b: usize,
+) -> usize {
+    if b < a {
+        get_edge(edges, edge_map, b, a)
+    } else {
+        // add this edge to the list if its not already there
+        match edge_map.get(&(a, b)) {
+            Some(&index) => index,
+
+            None => {
+                let index = edges.len();
+                edges.push((a, b));
+                edge_map.insert((a, b), index);
+                index
+            }
+        }
+    }
+}
+
+/// Load a mesh from a binary stl file.
+///
+/// Stl files repeat each vertex for every triangle that uses it, so vertices
+/// closer than a small tolerance are welded together to share edges.
+pub fn mk_meshes_stl(path: &str, color: Color) -> Result<Mesh, MeshError> {
+    const HEADER_SIZE: usize = 84;
+    const TRIANGLE_SIZE: usize = 50;
+
+    let bytes = fs::read(path).map_err(|e| MeshError::Io(e, path.to_string()))?;
+    let parse_error = |message: &str| MeshError::Parse(message.to_string(), path.to_string());
+
+    if bytes.len() < HEADER_SIZE {
+        return Err(parse_error("File is too short for an stl header"));
+    }
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    if bytes.len() < HEADER_SIZE + count * TRIANGLE_SIZE {
+        return Err(parse_error("File is too short for its triangle count"));
+    }
+
+    let read_r3 = |offset: usize| {
+        let f = |i: usize| {
+            let start = offset + 4 * i;
+            f32::from_le_bytes([
+                bytes[start],
+                bytes[start + 1],
+                bytes[start + 2],
+                bytes[start + 3],
+            ]) as f64
+        };
+        R3::new(f(0), f(1), f(2))
+    };
+
+    let mut vertices = Vec::new();
+    let mut buckets = HashMap::new();
+
+    let mut edge_map = HashMap::new();
+    let mut edges = Vec::new();
+    let mut triangles = Vec::new();
+    let mut normals = Vec::new();
+    let mut triangle_normals = Vec::new();
+    let face_color = face_color(color);
+
+    for t in 0..count {
+        let offset = HEADER_SIZE + t * TRIANGLE_SIZE;
+        let corners = [
+            read_r3(offset + 12),
+            read_r3(offset + 24),
+            read_r3(offset + 36),
+        ];
+
+        // lots of exporters leave the facet normal as zero
+        let normal = read_r3(offset);
+        normals.push(if normal == R3::zero() {
+            face_normal(&corners).normalized()
+        } else {
+            normal
+        });
+        triangle_normals.push([t, t, t]);
+
+        let [a, b, c] = corners.map(|v| weld(&mut vertices, &mut buckets, v));
+        let ab = get_edge(&mut edges, &mut edge_map, a, b);
+        let bc = get_edge(&mut edges, &mut edge_map, b, c);
+        let ca = get_edge(&mut edges, &mut edge_map, c, a);
+        triangles.push((
+            [
+                (ab, edges[ab].0 != a),
+                (bc, edges[bc].0 != b),
+                (ca, edges[ca].0 != c),
+            ],
+            face_color,
+        ));
+    }
+
+    Ok(Mesh {
+        vertices,
+        edges,
+        lines: Vec::new(),
+        triangles,
         parallelograms: Vec::new(),
+        normals,
+        triangle_normals,
+        parallelogram_normals: Vec::new(),
     })
 }
 
-#[derive(Debug)]
+/// Load a mesh from an stl file if the path ends in `.stl`, otherwise from an obj file
+pub fn load_mesh(path: &str, color: Color) -> Result<Mesh, MeshError> {
+    let is_stl = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("stl"));
+    if is_stl {
+        mk_meshes_stl(path, color)
+    } else {
+        mk_meshes(path, color)
+    }
+}
+
+/// Where a mesh comes from. Scene files give either a path string or a primitive.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize), serde(untagged))]
+pub enum MeshSource {
+    /// An obj or stl file, found with `resource_path`
+    File(String),
+    Primitive(Primitive),
+}
+
+/// The built in shapes, with the arguments of the functions of the same names
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "scene",
+    derive(Serialize, Deserialize),
+    serde(tag = "primitive", rename_all = "snake_case")
+)]
+// without the scene feature only the built in objects make these
+#[cfg_attr(not(feature = "scene"), allow(dead_code))]
+pub enum Primitive {
+    Cuboid {
+        size: R3,
+    },
+    UvSphere {
+        radius: f64,
+        rings: usize,
+        segments: usize,
+    },
+    Cylinder {
+        radius: f64,
+        height: f64,
+        segments: usize,
+    },
+    Cone {
+        radius: f64,
+        height: f64,
+        segments: usize,
+    },
+    Tetrahedron {
+        radius: f64,
+    },
+    Icosahedron {
+        radius: f64,
+    },
+}
+
+impl Primitive {
+    pub fn build(self, color: Color) -> Mesh {
+        match self {
+            Primitive::Cuboid { size } => cuboid(size, color),
+            Primitive::UvSphere {
+                radius,
+                rings,
+                segments,
+            } => uv_sphere(radius, rings, segments, color),
+            Primitive::Cylinder {
+                radius,
+                height,
+                segments,
+            } => cylinder(radius, height, segments, color),
+            Primitive::Cone {
+                radius,
+                height,
+                segments,
+            } => cone(radius, height, segments, color),
+            Primitive::Tetrahedron { radius } => tetrahedron(radius, color),
+            Primitive::Icosahedron { radius } => icosahedron(radius, color),
+        }
+    }
+}
+
+/// Everything needed to build an object's mesh again, so it can be saved
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
+pub struct MeshSpec {
+    #[cfg_attr(feature = "scene", serde(rename = "mesh"))]
+    pub source: MeshSource,
+    pub color: Color,
+    #[cfg_attr(feature = "scene", serde(default = "unit_scale"))]
+    pub scale: f64,
+}
+
+#[cfg(feature = "scene")]
+fn unit_scale() -> f64 {
+    1.0
+}
+
+impl MeshSpec {
+    /// Load or generate the mesh, merge anything duplicated and scale it
+    pub fn build(&self) -> Result<Mesh, MeshError> {
+        let mesh = match &self.source {
+            MeshSource::File(path) => {
+                load_mesh(&resource_path(path).to_string_lossy(), self.color)?
+            }
+            MeshSource::Primitive(primitive) => primitive.build(self.color),
+        };
+        Ok(scale(condense_mesh(&mesh), self.scale))
+    }
+}
+
+/// Build meshes one after another on a background thread. Each mesh is sent
+/// with its index in `specs` as soon as it is ready, so the receiver can show
+/// progress. A file that fails to load sends its error and the rest carry on.
+pub fn load_meshes_async(specs: Vec<MeshSpec>) -> mpsc::Receiver<Result<(usize, Mesh), MeshError>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for (i, spec) in specs.iter().enumerate() {
+            let result = spec.build().map(|mesh| (i, mesh));
+            if sender.send(result).is_err() {
+                // nobody is listening any more
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Save a mesh as an obj file.
+///
+/// Faces keep their winding, and `mesh.lines` are written as line elements.
+/// Colors are not saved.
+pub fn write_obj(mesh: &Mesh, path: &str) -> Result<(), MeshError> {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+
+    for v in &mesh.vertices {
+        writeln!(out, "v {} {} {}", v.x, v.y, v.z).unwrap();
+    }
+    for n in &mesh.normals {
+        writeln!(out, "vn {} {} {}", n.x, n.y, n.z).unwrap();
+    }
+
+    for &(ei, _) in &mesh.lines {
+        let (a, b) = mesh.edges[ei];
+        writeln!(out, "l {} {}", a + 1, b + 1).unwrap();
+    }
+
+    // obj indices start at 1
+    let mut write_face = |edges: &[(usize, bool)], normals: Option<&[usize]>| {
+        out.push('f');
+        for (i, &edge) in edges.iter().enumerate() {
+            let v = mesh.edge_start(edge) + 1;
+            match normals {
+                Some(ns) => write!(out, " {}//{}", v, ns[i] + 1).unwrap(),
+                None => write!(out, " {}", v).unwrap(),
+            }
+        }
+        out.push('\n');
+    };
+
+    for (i, (edges, _)) in mesh.triangles.iter().enumerate() {
+        write_face(edges, mesh.triangle_normals.get(i).map(|ns| &ns[..]));
+    }
+    for (i, (edges, _)) in mesh.parallelograms.iter().enumerate() {
+        write_face(edges, mesh.parallelogram_normals.get(i).map(|ns| &ns[..]));
+    }
+
+    fs::write(path, out).map_err(|e| MeshError::Io(e, path.to_string()))
+}
+
+#[derive(Clone, Debug)]
 pub struct Mesh {
     pub vertices: Vec<R3>,
     pub edges: Vec<(usize, usize)>,
     pub lines: Vec<(usize, Color)>,
     pub triangles: Vec<([(usize, bool); 3], Color)>,
     pub parallelograms: Vec<([(usize, bool); 4], Color)>,
+
+    /// Vertex normals loaded from a file, empty if it didn't have any
+    pub normals: Vec<R3>,
+    /// Index into `normals` for each corner of each triangle. This is either
+    /// empty or has one entry per triangle.
+    pub triangle_normals: Vec<[usize; 3]>,
+    /// Index into `normals` for each corner of each parallelogram, like `triangle_normals`
+    pub parallelogram_normals: Vec<[usize; 4]>,
+}
+
+impl Mesh {
+    /// The index of the vertex a face's `(edge, reversed)` entry starts from
+    pub fn edge_start(&self, (edge, reversed): (usize, bool)) -> usize {
+        if reversed {
+            self.edges[edge].1
+        } else {
+            self.edges[edge].0
+        }
+    }
+
+    /// The min and max corners of the mesh's axis aligned bounding box in
+    /// local space, or `None` if there are no vertices.
+    pub fn aabb(&self) -> Option<(R3, R3)> {
+        aabb(&self.vertices)
+    }
+
+    /// The center of the mesh's axis aligned bounding box in local space, or
+    /// `None` if there are no vertices.
+    pub fn center(&self) -> Option<R3> {
+        let (min, max) = self.aabb()?;
+        Some(midpoint(&min, &max))
+    }
+
+    /// The center and radius of a sphere in local space containing every
+    /// vertex, centered on the bounding box. An empty mesh gets a zero sphere.
+    pub fn bounding_sphere(&self) -> (R3, f64) {
+        bounding_sphere(&self.vertices)
+    }
+
+    /// Distance from the origin to the farthest vertex, so a sphere of this
+    /// radius around the origin contains the whole mesh
+    pub fn bounding_radius(&self) -> f64 {
+        self.vertices.iter().map(|v| v.norm()).fold(0.0, f64::max)
+    }
+}
+
+/// The min and max corners of the box around some points, or `None` if there aren't any
+pub fn aabb(vertices: &[R3]) -> Option<(R3, R3)> {
+    let first = *vertices.first()?;
+    Some(vertices.iter().fold((first, first), |(min, max), v| {
+        (
+            R3::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+            R3::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z)),
+        )
+    }))
+}
+
+/// The center and radius of a sphere containing every point, centered on
+/// their bounding box. No points gets a zero sphere.
+pub fn bounding_sphere(vertices: &[R3]) -> (R3, f64) {
+    let center = match aabb(vertices) {
+        Some((min, max)) => midpoint(&min, &max),
+        None => R3::zero(),
+    };
+    let radius = vertices
+        .iter()
+        .map(|v| v.distance(&center))
+        .fold(0.0, f64::max);
+    (center, radius)
 }
 
 pub fn scale(mesh: Mesh, factor: f64) -> Mesh {
@@ -142,20 +753,70 @@ pub fn scale(mesh: Mesh, factor: f64) -> Mesh {
     }
 }
 
+/// Move every vertex of the mesh by `offset`
+#[cfg(test)]
+pub fn translate(mesh: Mesh, offset: R3) -> Mesh {
+    Mesh {
+        vertices: mesh.vertices.iter().map(|p| *p + offset).collect(),
+        ..mesh
+    }
+}
+
+/// Scale each axis of the mesh by the matching component of `factors`
+#[cfg(test)]
+pub fn scale_nonuniform(mesh: Mesh, factors: R3) -> Mesh {
+    Mesh {
+        vertices: mesh
+            .vertices
+            .iter()
+            .map(|p| hadamard(p, &factors))
+            .collect(),
+        // normals scale by the inverse to stay perpendicular to the surface
+        normals: mesh
+            .normals
+            .iter()
+            .map(|n| R3::new(n.x / factors.x, n.y / factors.y, n.z / factors.z).normalized())
+            .collect(),
+        ..mesh
+    }
+}
+
+/// Bake a pose into the mesh, moving it from the object's frame into world space
+pub fn transform(mesh: Mesh, pose: &Pose) -> Mesh {
+    Mesh {
+        vertices: mesh
+            .vertices
+            .iter()
+            .map(|p| pose.orientation.rotate(p) + pose.pos)
+            .collect(),
+        // normals are directions, so they only rotate
+        normals: mesh
+            .normals
+            .iter()
+            .map(|n| pose.orientation.rotate(n))
+            .collect(),
+        ..mesh
+    }
+}
+
 /// A cuboid mesh with a given size and color
 pub fn cuboid(size: R3, color: Color) -> Mesh {
     let half_size = size * 0.5;
 
-    let vertices = vec![
-        R3::new(half_size.x, half_size.y, half_size.z),
-        R3::new(half_size.x, half_size.y, -half_size.z),
-        R3::new(half_size.x, -half_size.y, -half_size.z),
-        R3::new(half_size.x, -half_size.y, half_size.z),
-        R3::new(-half_size.x, -half_size.y, half_size.z),
-        R3::new(-half_size.x, -half_size.y, -half_size.z),
-        R3::new(-half_size.x, half_size.y, -half_size.z),
-        R3::new(-half_size.x, half_size.y, half_size.z),
+    let corners = [
+        [1.0, 1.0, 1.0],
+        [1.0, 1.0, -1.0],
+        [1.0, -1.0, -1.0],
+        [1.0, -1.0, 1.0],
+        [-1.0, -1.0, 1.0],
+        [-1.0, -1.0, -1.0],
+        [-1.0, 1.0, -1.0],
+        [-1.0, 1.0, 1.0],
     ];
+    let vertices = corners
+        .iter()
+        .map(|&signs| hadamard(&half_size, &R3::from(signs)))
+        .collect();
 
     let edges = vec![
         (0, 1),
@@ -174,17 +835,15 @@ pub fn cuboid(size: R3, color: Color) -> Mesh {
 
     let lines = (0..edges.len()).map(|i| (i, color)).collect();
 
-    let face_color = [color[0], color[1], color[2], 0.25 * color[3]];
+    let face_color = face_color(color);
+    // wound counter-clockwise when viewed from outside
     let parallelograms = vec![
-        ([(0, false), (1, false), (2, false), (8, true)], face_color),
+        ([(8, false), (2, true), (1, true), (0, true)], face_color),
         ([(0, false), (9, false), (6, false), (7, false)], face_color),
         ([(1, false), (10, false), (5, false), (9, true)], face_color),
         ([(2, false), (3, false), (4, false), (10, true)], face_color),
-        (
-            [(3, false), (11, false), (7, false), (8, false)],
-            face_color,
-        ),
-        ([(4, false), (5, false), (6, false), (11, true)], face_color),
+        ([(8, true), (7, true), (11, true), (3, true)], face_color),
+        ([(11, false), (6, true), (5, true), (4, true)], face_color),
     ];
 
     Mesh {
@@ -193,134 +852,1048 @@ pub fn cuboid(size: R3, color: Color) -> Mesh {
         lines,
         triangles: vec![],
         parallelograms,
+        normals: vec![],
+        triangle_normals: vec![],
+        parallelogram_normals: vec![],
     }
 }
 
-pub fn intersects_parallelogram(origin: &R3, direction: &R3, face: &[R3; 4]) -> bool {
+/// A mesh drawn only as lines between the given pairs of vertices
+fn wireframe(vertices: Vec<R3>, pairs: &[(usize, usize)], color: Color) -> Mesh {
+    let mut edge_map = HashMap::new();
+    let mut edges = Vec::new();
+    for &(a, b) in pairs {
+        get_edge(&mut edges, &mut edge_map, a, b);
+    }
+
+    let lines = (0..edges.len()).map(|i| (i, color)).collect();
+
+    Mesh {
+        vertices,
+        edges,
+        lines,
+        triangles: vec![],
+        parallelograms: vec![],
+        normals: vec![],
+        triangle_normals: vec![],
+        parallelogram_normals: vec![],
+    }
+}
+
+/// A point on a circle of `radius` around the z axis, `i` of `segments` of the way around
+fn circle_point(radius: f64, z: f64, i: usize, segments: usize) -> R3 {
+    let angle = 2.0 * std::f64::consts::PI * i as f64 / segments as f64;
+    R3::new(radius * angle.cos(), radius * angle.sin(), z)
+}
+
+/// A wireframe sphere centered on the origin with poles on the z axis.
+/// `rings` is the number of bands between the poles and `segments` the number
+/// of meridians.
+pub fn uv_sphere(radius: f64, rings: usize, segments: usize, color: Color) -> Mesh {
+    // the poles are shared by all the meridians, followed by the rings of latitude
+    let mut vertices = vec![R3::new(0.0, 0.0, radius), R3::new(0.0, 0.0, -radius)];
+    for ring in 1..rings {
+        let polar = std::f64::consts::PI * ring as f64 / rings as f64;
+        for i in 0..segments {
+            vertices.push(circle_point(
+                radius * polar.sin(),
+                radius * polar.cos(),
+                i,
+                segments,
+            ));
+        }
+    }
+
+    let index = |ring: usize, i: usize| {
+        if ring == 0 {
+            0
+        } else if ring == rings {
+            1
+        } else {
+            2 + (ring - 1) * segments + i % segments
+        }
+    };
+
+    let mut pairs = Vec::new();
+    for i in 0..segments {
+        for ring in 0..rings {
+            pairs.push((index(ring, i), index(ring + 1, i)));
+        }
+        for ring in 1..rings {
+            pairs.push((index(ring, i), index(ring, i + 1)));
+        }
+    }
+
+    wireframe(vertices, &pairs, color)
+}
+
+/// A wireframe cylinder centered on the origin with its axis along z
+pub fn cylinder(radius: f64, height: f64, segments: usize, color: Color) -> Mesh {
+    let half_height = 0.5 * height;
+    let vertices = (0..segments)
+        .map(|i| circle_point(radius, half_height, i, segments))
+        .chain((0..segments).map(|i| circle_point(radius, -half_height, i, segments)))
+        .collect();
+
+    let pairs: Vec<_> = (0..segments)
+        .flat_map(|i| {
+            let next = (i + 1) % segments;
+            vec![
+                (i, next),
+                (segments + i, segments + next),
+                (i, segments + i),
+            ]
+        })
+        .collect();
+
+    wireframe(vertices, &pairs, color)
+}
+
+/// A wireframe cone with its base centered below the origin and its tip
+/// above, along the z axis. Above is `-R3::UP`, the top of the screen.
+pub fn cone(radius: f64, height: f64, segments: usize, color: Color) -> Mesh {
+    let half_height = 0.5 * height;
+    let tip = segments;
+    let vertices = (0..segments)
+        .map(|i| circle_point(radius, half_height, i, segments))
+        .chain(std::iter::once(R3::new(0.0, 0.0, -half_height)))
+        .collect();
+
+    let pairs: Vec<_> = (0..segments)
+        .flat_map(|i| vec![(i, (i + 1) % segments), (i, tip)])
+        .collect();
+
+    wireframe(vertices, &pairs, color)
+}
+
+/// A square grid of lines in the xy plane centered on the origin, split into
+/// `divisions` cells along each side. The lines are split into a segment per
+/// cell side, so a point on the lattice is shared by the lines through it.
+pub fn grid(size: f64, divisions: usize, color: Color) -> Mesh {
+    let step = size / divisions as f64;
+    let offset = 0.5 * size;
+    let side = divisions + 1;
+    let index = |i: usize, j: usize| i * side + j;
+    let across = move |i: usize| i as f64 * step - offset;
+
+    let vertices = (0..side)
+        .flat_map(|i| (0..side).map(move |j| R3::new(across(i), across(j), 0.0)))
+        .collect();
+
+    let mut pairs = Vec::new();
+    for i in 0..side {
+        for j in 0..side {
+            if i + 1 < side {
+                pairs.push((index(i, j), index(i + 1, j)));
+            }
+            if j + 1 < side {
+                pairs.push((index(i, j), index(i, j + 1)));
+            }
+        }
+    }
+
+    wireframe(vertices, &pairs, color)
+}
+
+/// A mesh of triangles around the origin, with each face wound counter-clockwise
+/// when viewed from outside. Only works for convex shapes containing the origin.
+fn convex_triangle_mesh(vertices: Vec<R3>, faces: &[[usize; 3]], color: Color) -> Mesh {
+    let mut edge_map = HashMap::new();
+    let mut edges = Vec::new();
+    let face_color = face_color(color);
+
+    let triangles = faces
+        .iter()
+        .map(|&[a, b, c]| {
+            let corners = [vertices[a], vertices[b], vertices[c]];
+            let [a, b, c] = if dot(&face_normal(&corners), &corners[0]) < 0.0 {
+                [a, c, b]
+            } else {
+                [a, b, c]
+            };
+
+            let ab = get_edge(&mut edges, &mut edge_map, a, b);
+            let bc = get_edge(&mut edges, &mut edge_map, b, c);
+            let ca = get_edge(&mut edges, &mut edge_map, c, a);
+            (
+                [
+                    (ab, edges[ab].0 != a),
+                    (bc, edges[bc].0 != b),
+                    (ca, edges[ca].0 != c),
+                ],
+                face_color,
+            )
+        })
+        .collect();
+
+    let lines = (0..edges.len()).map(|i| (i, color)).collect();
+
+    Mesh {
+        vertices,
+        edges,
+        lines,
+        triangles,
+        parallelograms: vec![],
+        normals: vec![],
+        triangle_normals: vec![],
+        parallelogram_normals: vec![],
+    }
+}
+
+/// A regular tetrahedron centered on the origin with its vertices at `radius`
+pub fn tetrahedron(radius: f64, color: Color) -> Mesh {
+    let vertices = vec![
+        R3::new(1.0, 1.0, 1.0).normalized() * radius,
+        R3::new(1.0, -1.0, -1.0).normalized() * radius,
+        R3::new(-1.0, 1.0, -1.0).normalized() * radius,
+        R3::new(-1.0, -1.0, 1.0).normalized() * radius,
+    ];
+
+    convex_triangle_mesh(
+        vertices,
+        &[[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]],
+        color,
+    )
+}
+
+/// A regular icosahedron centered on the origin with its vertices at `radius`
+pub fn icosahedron(radius: f64, color: Color) -> Mesh {
+    // the corners of three orthogonal golden rectangles
+    let phi = 0.5 * (1.0 + 5.0_f64.sqrt());
+    let mut vertices = Vec::new();
+    for &(a, b) in &[(1.0, phi), (1.0, -phi), (-1.0, phi), (-1.0, -phi)] {
+        vertices.push(R3::new(0.0, a, b));
+        vertices.push(R3::new(a, b, 0.0));
+        vertices.push(R3::new(b, 0.0, a));
+    }
+
+    // every edge has length 2, and each face is three mutually adjacent vertices
+    let adjacent = |i: usize, j: usize| (vertices[i].distance(&vertices[j]) - 2.0).abs() < 1e-9;
+    let mut faces = Vec::new();
+    for a in 0..vertices.len() {
+        for b in a + 1..vertices.len() {
+            for c in b + 1..vertices.len() {
+                if adjacent(a, b) && adjacent(b, c) && adjacent(c, a) {
+                    faces.push([a, b, c]);
+                }
+            }
+        }
+    }
+
+    let vertices = vertices.iter().map(|v| v.normalized() * radius).collect();
+    convex_triangle_mesh(vertices, &faces, color)
+}
+
+/// Ray intersection with a parallelogram given by its corners in order.
+/// Returns the ray parameter `t` of the hit, which is the distance when `direction` is a unit vector.
+pub fn intersects_parallelogram(origin: &R3, direction: &R3, face: &[R3; 4]) -> Option<f64> {
     let [a, b, _, c] = *face;
 
     let normal = cross(&(a - b), &(a - c));
     let ao = a - *origin;
     let m = cross(direction, &ao);
 
+    let det = dot(direction, &normal);
+    if det == 0.0 {
+        // the ray is parallel to the face
+        return None;
+    }
+
     // divides are much more expensive than multiplies, so only do it once here
-    let invdet = 1.0 / dot(direction, &normal);
+    let invdet = 1.0 / det;
 
     let t = dot(&ao, &normal) * invdet;
     let u = dot(&(a - c), &m) * invdet;
     let v = -dot(&(a - b), &m) * invdet;
 
-    t >= 0.0 && u >= 0.0 && v >= 0.0 && u <= 1.0 && v <= 1.0
+    if t >= 0.0 && u >= 0.0 && v >= 0.0 && u <= 1.0 && v <= 1.0 {
+        Some(t)
+    } else {
+        None
+    }
 }
 
-pub fn intersects_triangle(origin: &R3, direction: &R3, face: &[R3; 3]) -> bool {
+/// Ray intersection with a triangle, see `intersects_parallelogram`.
+pub fn intersects_triangle(origin: &R3, direction: &R3, face: &[R3; 3]) -> Option<f64> {
     let [a, b, c] = *face;
 
     let normal = cross(&(a - b), &(a - c));
     let ao = a - *origin;
     let m = cross(direction, &ao);
 
+    let det = dot(direction, &normal);
+    if det == 0.0 {
+        // the ray is parallel to the face
+        return None;
+    }
+
     // divides are much more expensive than multiplies, so only do it once here
-    let invdet = 1.0 / dot(direction, &normal);
+    let invdet = 1.0 / det;
 
     let t = dot(&ao, &normal) * invdet;
     let u = dot(&(a - c), &m) * invdet;
     let v = -dot(&(a - b), &m) * invdet;
 
-    t >= 0.0 && u >= 0.0 && v >= 0.0 && u + v <= 1.0
+    if t >= 0.0 && u >= 0.0 && v >= 0.0 && u + v <= 1.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+fn map3<A, B>(xs: [A; 3], f: impl Fn(A) -> B) -> [B; 3] {
+    let [a, b, c] = xs;
+    [f(a), f(b), f(c)]
+}
+
+fn map4<A, B>(xs: [A; 4], f: impl Fn(A) -> B) -> [B; 4] {
+    let [a, b, c, d] = xs;
+    [f(a), f(b), f(c), f(d)]
+}
+
+/// The distance along a ray to the nearest face of a mesh placed at `pose`, and the index of that
+/// face, if it hits any. Triangles are numbered first, followed by parallelograms.
+pub fn raycast(mesh: &Mesh, pose: &Pose, origin: &R3, direction: &R3) -> Option<(f64, usize)> {
+    let world = |edge| {
+        pose.orientation
+            .rotate(&mesh.vertices[mesh.edge_start(edge)])
+            + pose.pos
+    };
+
+    let triangle_hits = mesh
+        .triangles
+        .iter()
+        .map(|&(edges, _)| intersects_triangle(origin, direction, &map3(edges, world)));
+    let parallelogram_hits = mesh
+        .parallelograms
+        .iter()
+        .map(|&(edges, _)| intersects_parallelogram(origin, direction, &map4(edges, world)));
+
+    triangle_hits
+        .chain(parallelogram_hits)
+        .enumerate()
+        .filter_map(|(face, hit)| hit.map(|t| (t, face)))
+        .fold(None, |nearest, (t, face)| match nearest {
+            Some((n, _)) if n <= t => nearest,
+            _ => Some((t, face)),
+        })
+}
+
+/// The vertices of a mesh placed at `pose`
+pub fn world_vertices(mesh: &Mesh, pose: &Pose) -> Vec<R3> {
+    #[cfg(feature = "rayon")]
+    let vertices = mesh.vertices.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let vertices = mesh.vertices.iter();
+
+    vertices
+        .map(|v| pose.orientation.rotate(v) + pose.pos)
+        .collect()
+}
+
+/// The (unnormalized) normal of a face from the winding of its first three vertices.
+/// Counter-clockwise faces, viewed from the front, point towards the viewer.
+pub fn face_normal(vs: &[R3]) -> R3 {
+    cross(&(vs[1] - vs[0]), &(vs[2] - vs[0]))
+}
+
+/// Checks if a face's front side points away from `eye`, see `face_normal`.
+pub fn faces_away(vs: &[R3], eye: &R3) -> bool {
+    dot(&face_normal(vs), &(vs[0] - *eye)) > 0.0
+}
+
+/// Distance from the camera to the centroid of a face, used to draw faces back to front
+pub fn face_depth(vs: &[R3], camera: &Camera) -> f64 {
+    let centroid = vs.iter().sum::<R3>() / vs.len() as f64;
+    centroid.distance(&camera.position)
+}
+
+/// A mesh as it lands on the screen, colored and ready to draw without
+/// looking at the geometry again. See `screen_mesh`.
+pub struct ScreenMesh {
+    /// The curve of each line and the color to draw it in
+    pub lines: Vec<(Color, Vec<[f64; 2]>)>,
+    /// The outline of each face, its color and whether it wraps around behind
+    /// the camera, in the order to draw them: back to front
+    pub faces: Vec<(Color, Vec<[f64; 2]>, bool)>,
+}
+
+/// Project, shade and sort a mesh whose vertices have already been moved into
+/// world space, see `world_vertices`. Lines or faces the style leaves out come back empty.
+pub fn screen_mesh(
+    mesh: &Mesh,
+    transformed_vertices: &[R3],
+    options: &RenderOptions,
+    highlight: Option<Color>,
+    camera: Camera,
+) -> ScreenMesh {
+    let (center, radius) = bounding_sphere(transformed_vertices);
+    let max_split = lod_max_split(radius, center.distance(&camera.position), &camera, options);
+    let curves = project_edges(
+        mesh,
+        transformed_vertices,
+        camera,
+        options.resolution,
+        max_split,
+    );
+
+    let mut lines = Vec::new();
+    if options.style.draws_lines() {
+        for (ci, color) in &mesh.lines {
+            let color = highlight.unwrap_or(*color);
+            let color = match options.fog {
+                Some(fog) => {
+                    let (a, b) = mesh.edges[*ci];
+                    let edge_center = midpoint(&transformed_vertices[a], &transformed_vertices[b]);
+                    fog.apply(color, edge_center.distance(&camera.position))
+                }
+                None => color,
+            };
+            lines.push((color, curves[*ci].clone()));
+        }
+    }
+    if !options.style.draws_faces() {
+        return ScreenMesh {
+            lines,
+            faces: Vec::new(),
+        };
+    }
+
+    // only the angular projection wraps faces around the edge of the screen
+    let wraps_behind = camera.projection == ProjectionMode::Angular;
+    let backward = camera.orientation.rotate(&-R3::FORWARD);
+
+    let start_vertex = |edge| transformed_vertices[mesh.edge_start(edge)];
+
+    let shaded = |vs: &[R3], color: Color| {
+        let color = match options.light_dir {
+            Some(light_dir) => shade(color, lambert(&face_normal(vs), &light_dir)),
+            None => color,
+        };
+        match options.fog {
+            Some(fog) => fog.apply(color, face_depth(vs, &camera)),
+            None => color,
+        }
+    };
+
+    // collect all the faces so they can be drawn back to front
+    let mut faces = Vec::new();
+
+    for (edge_indices, color) in &mesh.triangles {
+        let vs = map3(*edge_indices, start_vertex);
+        if options.cull_backfaces && faces_away(&vs, &camera.position) {
+            continue;
+        }
+        let is_behind =
+            wraps_behind && intersects_triangle(&camera.position, &backward, &vs).is_some();
+        // faces that wrap around behind the camera go first so the stencil inversion still works
+        let depth = if is_behind {
+            f64::INFINITY
+        } else {
+            face_depth(&vs, &camera)
+        };
+        faces.push((depth, &edge_indices[..], shaded(&vs, *color), is_behind));
+    }
+
+    for (edge_indices, color) in &mesh.parallelograms {
+        let vs = map4(*edge_indices, start_vertex);
+        if options.cull_backfaces && faces_away(&vs, &camera.position) {
+            continue;
+        }
+        let is_behind =
+            wraps_behind && intersects_parallelogram(&camera.position, &backward, &vs).is_some();
+        let depth = if is_behind {
+            f64::INFINITY
+        } else {
+            face_depth(&vs, &camera)
+        };
+        faces.push((depth, &edge_indices[..], shaded(&vs, *color), is_behind));
+    }
+
+    // sort is stable, so ties keep the mesh order
+    faces.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    let faces = faces
+        .into_iter()
+        .filter_map(|(_, edge_indices, color, is_behind)| {
+            let mut points = Vec::new();
+            for &(ci, rev) in edge_indices {
+                if rev {
+                    points.extend(curves[ci].iter().rev());
+                } else {
+                    points.extend(&curves[ci]);
+                }
+            }
+            // fewer points means it was clipped away entirely
+            if points.len() < 3 {
+                None
+            } else {
+                Some((color, points, is_behind))
+            }
+        })
+        .collect();
+
+    ScreenMesh { lines, faces }
 }
 
+/// Draw a mesh whose vertices have already been moved into world space, see `world_vertices`
+#[allow(clippy::too_many_arguments)]
 pub fn render_mesh(
     mesh: &Mesh,
-    pose: &Pose,
-    debug: bool,
+    transformed_vertices: &[R3],
+    options: &RenderOptions,
+    highlight: Option<Color>,
     context: &graphics::Context,
     g: &mut opengl_graphics::GlGraphics,
     camera: Camera,
     center: graphics::math::Matrix2d,
 ) {
-    const RESOLUTION: f64 = 40.0;
-    const MAX_SPLIT: i32 = 9;
+    let screen = screen_mesh(mesh, transformed_vertices, options, highlight, camera);
 
-    let transformed_vertices = mesh
-        .vertices
-        .iter()
-        .map(|v| pose.orientation.rotate(v) + pose.pos)
-        .collect::<Vec<_>>();
+    for (color, curve) in &screen.lines {
+        render_curve(*color, curve, options.debug, context, g, center);
+    }
+    for (color, points, is_behind) in &screen.faces {
+        draw_poly(*color, points, *is_behind, &camera, context, center, g);
+    }
+}
 
-    let curves = mesh
-        .edges
-        .iter()
-        .map(|(ai, bi)| {
-            approximate_curve(
-                &transformed_vertices[*ai],
-                &transformed_vertices[*bi],
-                camera,
-                RESOLUTION,
-                MAX_SPLIT,
-            )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quaternion::Quaternion;
+
+    #[test]
+    fn obj_quads_become_parallelograms() {
+        let mesh = mk_meshes("data/quad.obj", [1.0; 4]).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.edges.len(), 4);
+        assert_eq!(mesh.parallelograms.len(), 1);
+        assert!(mesh.triangles.is_empty());
+        assert!(mesh.lines.is_empty());
+    }
+
+    #[test]
+    fn obj_quads_that_arent_flat_become_triangles() {
+        let mesh = mk_meshes("data/skewed-quad.obj", [1.0; 4]).unwrap();
+        // the diagonal is shared by the two halves
+        assert_eq!(mesh.edges.len(), 5);
+        assert_eq!(mesh.triangles.len(), 2);
+        assert!(mesh.parallelograms.is_empty());
+    }
+
+    #[test]
+    fn obj_materials_color_their_groups() {
+        let mesh = mk_meshes("data/two-materials.obj", [0.0, 1.0, 0.0, 1.0]).unwrap();
+        assert_eq!(mesh.triangles.len(), 2);
+
+        let (_, red) = mesh.triangles[0];
+        let (_, blue) = mesh.triangles[1];
+        assert_ne!(red, blue);
+        assert_eq!(red, face_color([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(blue, face_color([0.0, 0.0, 1.0, 0.5]));
+    }
+
+    #[test]
+    fn obj_lines_without_a_material_keep_the_given_color() {
+        let green = [0.0, 1.0, 0.0, 1.0];
+        let mesh = mk_meshes("data/two-materials.obj", green).unwrap();
+        let colors: Vec<Color> = mesh.lines.iter().map(|&(_, color)| color).collect();
+        assert_eq!(colors, vec![green, [1.0, 0.0, 0.0, 1.0]]);
+    }
+
+    #[test]
+    fn obj_files_with_a_missing_material_library_still_load() {
+        let path = std::env::temp_dir().join("fieldgame-missing-library.obj");
+        fs::write(
+            &path,
+            "mtllib nowhere.mtl\nv 0 0 0\nv 1 0 0\nusemtl red\nl 1 2\n",
+        )
+        .unwrap();
+
+        let green = [0.0, 1.0, 0.0, 1.0];
+        let mesh = mk_meshes(&path.to_string_lossy(), green).unwrap();
+        assert_eq!(mesh.lines, vec![(0, green)]);
+    }
+
+    #[test]
+    fn missing_files_name_the_path_in_the_error() {
+        for path in ["data/fieldgame-nowhere.obj", "data/fieldgame-nowhere.stl"] {
+            match load_mesh(path, [1.0; 4]) {
+                Err(e @ MeshError::Io(..)) => {
+                    let message = e.to_string();
+                    assert!(message.starts_with(&format!("Could not read {}: ", path)));
+                }
+                _ => panic!("{} should not load", path),
+            }
+        }
+    }
+
+    #[test]
+    fn stl_files_load_their_triangles() {
+        let mesh = load_mesh("data/triangle.stl", [1.0; 4]).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.edges.len(), 3);
+        assert_eq!(mesh.triangles.len(), 1);
+        assert_eq!(mesh.normals, vec![R3::new(0.0, 0.0, 1.0)]);
+    }
+
+    /// Load the start of the triangle fixture with its count replaced by `count`
+    fn load_stl_prefix(name: &str, length: usize, count: u32) -> Result<Mesh, MeshError> {
+        let mut bytes = fs::read("data/triangle.stl").unwrap();
+        bytes[80..84].copy_from_slice(&count.to_le_bytes());
+        bytes.truncate(length);
+
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, bytes).unwrap();
+        mk_meshes_stl(&path.to_string_lossy(), [1.0; 4])
+    }
+
+    #[test]
+    fn truncated_stl_files_are_rejected() {
+        let short_header = load_stl_prefix("fieldgame-short-header.stl", 60, 1);
+        assert!(matches!(short_header, Err(MeshError::Parse(..))));
+
+        let short_triangle = load_stl_prefix("fieldgame-short-triangle.stl", 100, 1);
+        assert!(matches!(short_triangle, Err(MeshError::Parse(..))));
+    }
+
+    #[test]
+    fn stl_counts_past_the_end_of_the_file_are_rejected() {
+        let result = load_stl_prefix("fieldgame-oversized-count.stl", 134, u32::MAX);
+        match result {
+            Err(MeshError::Parse(message, _)) => assert!(message.contains("triangle count")),
+            _ => panic!("an stl claiming more triangles than it has should not load"),
+        }
+    }
+
+    /// Checks every face of a convex mesh around the origin faces outwards
+    fn faces_point_outwards(mesh: &Mesh) -> bool {
+        mesh.triangles.iter().all(|(edges, _)| {
+            let vs = edges.map(|edge| mesh.vertices[mesh.edge_start(edge)]);
+            dot(&face_normal(&vs), &vs[0]) > 0.0
         })
-        .collect::<Vec<_>>();
+    }
 
-    for (ci, color) in &mesh.lines {
-        render_curve(*color, &curves[*ci], debug, context, g, center);
+    #[test]
+    fn icosahedron_has_12_vertices_30_edges_and_20_faces() {
+        let mesh = icosahedron(2.0, [1.0; 4]);
+        assert_eq!(mesh.vertices.len(), 12);
+        assert_eq!(mesh.edges.len(), 30);
+        assert_eq!(mesh.triangles.len(), 20);
+        assert!(mesh.vertices.iter().all(|v| (v.norm() - 2.0).abs() < 1e-12));
+        assert!(faces_point_outwards(&mesh));
     }
 
-    let backward = camera.orientation.rotate(&R3 {
-        x: -1.0,
-        y: 0.0,
-        z: 0.0,
-    });
+    #[test]
+    fn tetrahedron_has_4_vertices_6_edges_and_4_faces() {
+        let mesh = tetrahedron(2.0, [1.0; 4]);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.edges.len(), 6);
+        assert_eq!(mesh.triangles.len(), 4);
+        assert!(faces_point_outwards(&mesh));
+    }
 
-    fn map3<A, B>(xs: [A; 3], f: impl Fn(A) -> B) -> [B; 3] {
-        let [a, b, c] = xs;
-        [f(a), f(b), f(c)]
+    #[test]
+    fn uv_sphere_vertices_are_on_the_sphere() {
+        let mesh = uv_sphere(3.0, 4, 6, [1.0; 4]);
+        // two poles and 3 rings of 6
+        assert_eq!(mesh.vertices.len(), 20);
+        // 6 meridians of 4 segments and 3 rings of 6
+        assert_eq!(mesh.edges.len(), 42);
+        assert_eq!(mesh.lines.len(), 42);
+        assert!(mesh.vertices.iter().all(|v| (v.norm() - 3.0).abs() < 1e-12));
     }
-    for &(edge_indices, color) in &mesh.triangles {
-        let vs = map3(edge_indices, |(ei, rev)| {
-            transformed_vertices[if rev {
-                mesh.edges[ei].1
-            } else {
-                mesh.edges[ei].0
-            }]
-        });
-        let is_behind = intersects_triangle(&camera.position, &backward, &vs);
 
-        let mut points = Vec::new();
-        for &(ci, rev) in &edge_indices {
-            if rev {
-                points.extend(curves[ci].iter().rev());
-            } else {
-                points.extend(&curves[ci]);
+    #[test]
+    fn cylinders_have_two_rings_joined_by_sides() {
+        let mesh = cylinder(1.0, 2.0, 8, [1.0; 4]);
+        assert_eq!(mesh.vertices.len(), 16);
+        assert_eq!(mesh.edges.len(), 24);
+        assert!(mesh.vertices.iter().all(|v| v.z.abs() == 1.0));
+    }
+
+    #[test]
+    fn cones_have_a_ring_joined_to_the_tip() {
+        let mesh = cone(1.0, 2.0, 8, [1.0; 4]);
+        assert_eq!(mesh.vertices.len(), 9);
+        assert_eq!(mesh.edges.len(), 16);
+        assert_eq!(mesh.vertices[8], R3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn three_cuboid_faces_survive_culling_from_a_corner() {
+        let mesh = cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]);
+        let position = R3::new(10.0, 8.0, -6.0);
+        let camera = Camera {
+            position,
+            orientation: Quaternion::look_at(-position, -R3::UP),
+            scale: 500.0,
+            projection: ProjectionMode::Perspective { fov: 1.2 },
+        };
+        let culled = RenderOptions {
+            cull_backfaces: true,
+            ..RenderOptions::default()
+        };
+
+        let faces = screen_mesh(&mesh, &mesh.vertices, &culled, None, camera).faces;
+        assert_eq!(faces.len(), 3);
+        let all = screen_mesh(
+            &mesh,
+            &mesh.vertices,
+            &RenderOptions::default(),
+            None,
+            camera,
+        );
+        assert_eq!(all.faces.len(), 6);
+    }
+
+    #[test]
+    fn rays_hit_faces_in_front_of_them() {
+        let triangle = [
+            R3::new(0.0, 0.0, 5.0),
+            R3::new(1.0, 0.0, 5.0),
+            R3::new(0.0, 1.0, 5.0),
+        ];
+        let square = [
+            R3::new(0.0, 0.0, 5.0),
+            R3::new(1.0, 0.0, 5.0),
+            R3::new(1.0, 1.0, 5.0),
+            R3::new(0.0, 1.0, 5.0),
+        ];
+        let up = R3::new(0.0, 0.0, 1.0);
+
+        let inside = R3::new(0.2, 0.2, 0.0);
+        assert_eq!(intersects_triangle(&inside, &up, &triangle), Some(5.0));
+        assert_eq!(intersects_parallelogram(&inside, &up, &square), Some(5.0));
+
+        // past the diagonal is only in the square
+        let corner = R3::new(0.8, 0.8, 0.0);
+        assert_eq!(intersects_triangle(&corner, &up, &triangle), None);
+        assert_eq!(intersects_parallelogram(&corner, &up, &square), Some(5.0));
+
+        let outside = R3::new(1.5, 0.5, 0.0);
+        assert_eq!(intersects_parallelogram(&outside, &up, &square), None);
+        assert_eq!(intersects_triangle(&inside, &-up, &triangle), None);
+        assert_eq!(intersects_parallelogram(&inside, &-up, &square), None);
+    }
+
+    #[test]
+    fn rays_parallel_to_a_face_miss_it() {
+        let triangle = [
+            R3::new(0.0, 0.0, 0.0),
+            R3::new(1.0, 0.0, 0.0),
+            R3::new(0.0, 1.0, 0.0),
+        ];
+        let square = [
+            R3::new(0.0, 0.0, 0.0),
+            R3::new(1.0, 0.0, 0.0),
+            R3::new(1.0, 1.0, 0.0),
+            R3::new(0.0, 1.0, 0.0),
+        ];
+        // even when the ray lies in the face's plane
+        let origin = R3::new(-1.0, 0.2, 0.0);
+        let along = R3::new(1.0, 0.0, 0.0);
+        assert_eq!(intersects_triangle(&origin, &along, &triangle), None);
+        assert_eq!(intersects_parallelogram(&origin, &along, &square), None);
+    }
+
+    #[test]
+    fn raycast_finds_the_nearest_cuboid_face() {
+        let mesh = cuboid(R3::new(2.0, 4.0, 6.0), [1.0; 4]);
+        let forward = R3::new(1.0, 0.0, 0.0);
+        let pose = Pose {
+            pos: R3::new(10.0, 0.0, 0.0),
+            orientation: Quaternion::zero_rotation(),
+        };
+        let (distance, face) = raycast(&mesh, &pose, &R3::zero(), &forward).unwrap();
+        assert!((distance - 9.0).abs() < 1e-12);
+        // the face that was hit is the one facing the ray
+        let (edges, _) = mesh.parallelograms[face - mesh.triangles.len()];
+        let vs = edges.map(|edge| mesh.vertices[mesh.edge_start(edge)] + pose.pos);
+        assert!(vs.iter().all(|v| (v.x - 9.0).abs() < 1e-12));
+
+        // turned a quarter, the long side faces the ray
+        let turned = Pose {
+            orientation: Quaternion::rotation(R3::new(0.0, 0.0, 1.0), std::f64::consts::FRAC_PI_2),
+            ..pose
+        };
+        let (distance, _) = raycast(&mesh, &turned, &R3::zero(), &forward).unwrap();
+        assert!((distance - 8.0).abs() < 1e-12);
+
+        assert_eq!(raycast(&mesh, &pose, &R3::zero(), &-forward), None);
+    }
+
+    /// Every part of `meshes` in one mesh, without merging anything
+    fn combine(meshes: &[Mesh]) -> Mesh {
+        let mut combined = Mesh {
+            vertices: vec![],
+            edges: vec![],
+            lines: vec![],
+            triangles: vec![],
+            parallelograms: vec![],
+            normals: vec![],
+            triangle_normals: vec![],
+            parallelogram_normals: vec![],
+        };
+        for mesh in meshes {
+            let (v, e) = (combined.vertices.len(), combined.edges.len());
+            let shift = |face: &[(usize, bool)]| -> Vec<(usize, bool)> {
+                face.iter()
+                    .map(|&(edge, reversed)| (edge + e, reversed))
+                    .collect()
+            };
+            combined.vertices.extend(&mesh.vertices);
+            combined
+                .edges
+                .extend(mesh.edges.iter().map(|&(a, b)| (a + v, b + v)));
+            combined
+                .lines
+                .extend(mesh.lines.iter().map(|&(edge, color)| (edge + e, color)));
+            for (face, color) in &mesh.triangles {
+                let face = shift(face);
+                combined
+                    .triangles
+                    .push(([face[0], face[1], face[2]], *color));
+            }
+            for (face, color) in &mesh.parallelograms {
+                let face = shift(face);
+                combined
+                    .parallelograms
+                    .push(([face[0], face[1], face[2], face[3]], *color));
             }
         }
+        combined
+    }
 
-        draw_poly(color, &points, is_behind, &context.draw_state, center, g);
+    #[test]
+    fn condense_mesh_merges_duplicate_edges_lines_and_faces() {
+        let box_mesh = cuboid(R3::new(1.0, 2.0, 3.0), [1.0; 4]);
+        let twice = combine(&[box_mesh.clone(), box_mesh.clone()]);
+        assert_eq!(twice.vertices.len(), 16);
+
+        let condensed = condense_mesh(&twice);
+        assert_eq!(condensed.vertices.len(), 8);
+        assert_eq!(condensed.edges.len(), box_mesh.edges.len());
+        assert_eq!(condensed.lines.len(), box_mesh.lines.len());
+        assert_eq!(condensed.parallelograms.len(), 6);
+        assert_eq!(face_frames(&condensed), face_frames(&box_mesh));
+
+        let ball = icosahedron(1.0, [1.0; 4]);
+        let condensed = condense_mesh(&combine(&[ball.clone(), ball]));
+        assert_eq!(condensed.vertices.len(), 12);
+        assert_eq!(condensed.edges.len(), 30);
+        assert_eq!(condensed.triangles.len(), 20);
     }
 
-    fn map4<A, B>(xs: [A; 4], f: impl Fn(A) -> B) -> [B; 4] {
-        let [a, b, c, d] = xs;
-        [f(a), f(b), f(c), f(d)]
+    #[test]
+    fn nearly_equal_vertices_are_welded() {
+        let box_mesh = cuboid(R3::new(1.0, 2.0, 3.0), [1.0; 4]);
+        let nudged = translate(box_mesh.clone(), R3::new(1e-12, -1e-12, 1e-12));
+        let condensed = condense_mesh(&combine(&[box_mesh.clone(), nudged]));
+        assert_eq!(condensed.vertices.len(), 8);
+        assert_eq!(condensed.edges.len(), 12);
+        assert_eq!(condensed.parallelograms.len(), 6);
+
+        // further apart than the tolerance stays separate
+        let moved = translate(box_mesh.clone(), R3::new(10.0 * WELD_TOLERANCE, 0.0, 0.0));
+        let condensed = condense_mesh(&combine(&[box_mesh, moved]));
+        assert_eq!(condensed.vertices.len(), 16);
     }
-    for &(edge_indices, color) in &mesh.parallelograms {
-        let vs = map4(edge_indices, |(ei, rev)| {
-            transformed_vertices[if rev {
-                mesh.edges[ei].1
-            } else {
-                mesh.edges[ei].0
-            }]
-        });
-        let is_behind = intersects_parallelogram(&camera.position, &backward, &vs);
 
-        let mut points = Vec::new();
-        for &(ci, rev) in &edge_indices {
-            if rev {
-                points.extend(curves[ci].iter().rev());
-            } else {
-                points.extend(&curves[ci]);
+    #[test]
+    fn welding_finds_duplicates_in_neighboring_cells() {
+        let mut buckets = HashMap::new();
+        let mut vertices = Vec::new();
+        let lattice: Vec<R3> = (0..1000)
+            .map(|i| R3::new((i % 10) as f64, (i / 10 % 10) as f64, (i / 100) as f64))
+            .collect();
+
+        // each copy is nudged less than the tolerance, either side of the cell edges
+        for copy in 0..5 {
+            let nudge = (copy as f64 - 2.0) * 0.2 * WELD_TOLERANCE;
+            for (i, &p) in lattice.iter().enumerate() {
+                let index = weld(
+                    &mut vertices,
+                    &mut buckets,
+                    p + R3::new(nudge, -nudge, nudge),
+                );
+                assert_eq!(index, i);
             }
         }
+        assert_eq!(vertices.len(), 1000);
+    }
+
+    /// The center and unit normal of every face, in mesh order
+    fn face_frames(mesh: &Mesh) -> Vec<(R3, R3)> {
+        let corners = |edges: &[(usize, bool)]| -> Vec<R3> {
+            edges
+                .iter()
+                .map(|&edge| mesh.vertices[mesh.edge_start(edge)])
+                .collect()
+        };
+        let frame = |vs: Vec<R3>| {
+            (
+                vs.iter().sum::<R3>() / vs.len() as f64,
+                face_normal(&vs).normalized(),
+            )
+        };
+
+        let triangles = mesh
+            .triangles
+            .iter()
+            .map(|(edges, _)| frame(corners(edges)));
+        let parallelograms = mesh
+            .parallelograms
+            .iter()
+            .map(|(edges, _)| frame(corners(edges)));
+        triangles.chain(parallelograms).collect()
+    }
+
+    /// Write a mesh to an obj file and read it back
+    fn round_trip(mesh: &Mesh, name: &str) -> Mesh {
+        let path = std::env::temp_dir().join(name);
+        let path = path.to_string_lossy();
+        write_obj(mesh, &path).unwrap();
+        mk_meshes(&path, [1.0; 4]).unwrap()
+    }
+
+    #[test]
+    fn cuboids_round_trip_through_obj_files() {
+        let mesh = cuboid(R3::new(1.0, 2.0, 3.0), [1.0; 4]);
+        let back = round_trip(&mesh, "fieldgame-cuboid.obj");
 
-        draw_poly(color, &points, is_behind, &context.draw_state, center, g);
+        assert_eq!(back.vertices, mesh.vertices);
+        assert_eq!(back.edges.len(), mesh.edges.len());
+        assert_eq!(back.lines.len(), mesh.lines.len());
+        assert_eq!(back.parallelograms.len(), 6);
+        // the winding, and so the normals, survive
+        for ((center, normal), (back_center, back_normal)) in
+            face_frames(&mesh).iter().zip(&face_frames(&back))
+        {
+            assert!(center.approx_eq(back_center, 1e-12));
+            assert!(normal.approx_eq(back_normal, 1e-12));
+        }
+    }
+
+    #[test]
+    fn triangle_meshes_round_trip_through_obj_files() {
+        let mesh = icosahedron(1.0, [1.0; 4]);
+        let back = round_trip(&mesh, "fieldgame-icosahedron.obj");
+
+        assert_eq!(back.vertices.len(), 12);
+        assert_eq!(back.edges.len(), 30);
+        assert_eq!(back.triangles.len(), 20);
+        assert_eq!(face_frames(&back), face_frames(&mesh));
+    }
+
+    #[test]
+    fn grid_is_a_lattice_of_cell_sides() {
+        let lattice = grid(4.0, 2, [1.0; 4]);
+        // 3 lines of 2 segments each way over a 3x3 lattice
+        assert_eq!(lattice.vertices.len(), 9);
+        assert_eq!(lattice.edges.len(), 12);
+        assert_eq!(lattice.lines.len(), 12);
+        for &(a, b) in &lattice.edges {
+            assert_eq!(lattice.vertices[a].distance(&lattice.vertices[b]), 2.0);
+        }
+        assert_eq!(
+            lattice.aabb(),
+            Some((R3::new(-2.0, -2.0, 0.0), R3::new(2.0, 2.0, 0.0)))
+        );
+
+        // a single cell is just its outline
+        let cell = grid(4.0, 1, [1.0; 4]);
+        assert_eq!(cell.vertices.len(), 4);
+        assert_eq!(cell.edges.len(), 4);
+    }
+
+    #[test]
+    fn transform_rotates_then_moves_the_vertices() {
+        let mesh = load_mesh("data/triangle.stl", [1.0; 4]).unwrap();
+        // a quarter turn about x takes y to z
+        let quarter_turn =
+            Quaternion::rotation(R3::new(1.0, 0.0, 0.0), std::f64::consts::FRAC_PI_2);
+        let pose = Pose {
+            pos: R3::new(1.0, 2.0, 3.0),
+            orientation: quarter_turn,
+        };
+        let moved = transform(mesh.clone(), &pose);
+
+        for (v, moved_v) in mesh.vertices.iter().zip(&moved.vertices) {
+            let expected = R3::new(v.x, -v.z, v.y) + pose.pos;
+            assert!(moved_v.approx_eq(&expected, 1e-12));
+        }
+        // normals turn with the mesh but don't move
+        assert!(moved.normals[0].approx_eq(&R3::new(0.0, -1.0, 0.0), 1e-12));
+        assert_eq!(moved.edges, mesh.edges);
+    }
+
+    #[test]
+    fn translate_moves_every_vertex_by_the_offset() {
+        let mesh = cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]);
+        let offset = R3::new(1.0, -2.0, 3.0);
+        let moved = translate(mesh.clone(), offset);
+
+        for (v, moved_v) in mesh.vertices.iter().zip(&moved.vertices) {
+            assert_eq!(*moved_v, *v + offset);
+        }
+        assert_eq!(moved.edges, mesh.edges);
+        assert_eq!(moved.parallelograms, mesh.parallelograms);
+    }
+
+    #[test]
+    fn scale_nonuniform_stretches_each_axis() {
+        let mesh = load_mesh("data/triangle.stl", [1.0; 4]).unwrap();
+        let factors = R3::new(2.0, 3.0, 4.0);
+        let scaled = scale_nonuniform(mesh.clone(), factors);
+
+        for (v, scaled_v) in mesh.vertices.iter().zip(&scaled.vertices) {
+            assert_eq!(*scaled_v, R3::new(2.0 * v.x, 3.0 * v.y, 4.0 * v.z));
+        }
+        // normals stay unit length
+        assert!(scaled.normals[0].approx_eq(&R3::new(0.0, 0.0, 1.0), 1e-12));
+    }
+
+    #[test]
+    fn cuboid_bounds_are_half_its_size_each_way() {
+        let mesh = cuboid(R3::new(2.0, 4.0, 6.0), [1.0; 4]);
+        assert_eq!(
+            mesh.aabb(),
+            Some((R3::new(-1.0, -2.0, -3.0), R3::new(1.0, 2.0, 3.0)))
+        );
+        assert_eq!(aabb(&[]), None);
+    }
+
+    #[test]
+    fn bounding_sphere_is_centered_on_the_cuboid() {
+        let mesh = translate(
+            cuboid(R3::new(2.0, 4.0, 4.0), [1.0; 4]),
+            R3::new(10.0, 0.0, 0.0),
+        );
+        let (center, radius) = mesh.bounding_sphere();
+        assert!(center.approx_eq(&R3::new(10.0, 0.0, 0.0), 1e-12));
+        assert!((radius - 3.0).abs() < 1e-12);
+        // the sphere around the origin has to reach past the far side
+        assert!(mesh.bounding_radius() > 11.0);
+        assert_eq!(mesh.center(), Some(R3::new(10.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn bounding_spheres_contain_every_vertex() {
+        let meshes = [
+            translate(icosahedron(2.0, [1.0; 4]), R3::new(1.0, -3.0, 2.0)),
+            uv_sphere(1.5, 5, 7, [1.0; 4]),
+            cone(1.0, 4.0, 9, [1.0; 4]),
+            load_mesh("data/octahedron.obj", [1.0; 4]).unwrap(),
+        ];
+        for mesh in &meshes {
+            let (center, radius) = mesh.bounding_sphere();
+            assert_eq!(Some(center), mesh.center());
+            assert!(mesh.vertices.iter().all(|v| v.distance(&center) <= radius));
+            // and it is no bigger than it needs to be
+            assert!(mesh.vertices.iter().any(|v| v.distance(&center) == radius));
+        }
+        assert_eq!(bounding_sphere(&[]), (R3::zero(), 0.0));
     }
 }
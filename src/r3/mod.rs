@@ -1,4 +1,5 @@
 pub mod pose;
 pub mod quaternion;
+#[allow(clippy::module_inception)]
 pub mod r3;
 pub use r3::*;
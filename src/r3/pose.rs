@@ -1,7 +1,11 @@
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
+
 use super::quaternion::Quaternion;
 use super::r3::R3;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
 pub struct Pose {
     pub pos: R3,
     pub orientation: Quaternion,
@@ -1,8 +1,11 @@
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
 use std::ops;
 
 use super::r3::*;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
 pub struct Quaternion {
     pub r: f64,
     pub i: f64,
@@ -25,6 +28,45 @@ impl ops::Mul<Quaternion> for Quaternion {
     }
 }
 
+impl ops::Mul<f64> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, other: f64) -> Self::Output {
+        Quaternion {
+            r: self.r * other,
+            i: self.i * other,
+            j: self.j * other,
+            k: self.k * other,
+        }
+    }
+}
+
+impl ops::Add<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, other: Quaternion) -> Self::Output {
+        Quaternion {
+            r: self.r + other.r,
+            i: self.i + other.i,
+            j: self.j + other.j,
+            k: self.k + other.k,
+        }
+    }
+}
+
+impl ops::Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Self::Output {
+        Quaternion {
+            r: -self.r,
+            i: -self.i,
+            j: -self.j,
+            k: -self.k,
+        }
+    }
+}
+
 impl ops::Div<f64> for Quaternion {
     type Output = Quaternion;
 
@@ -61,6 +103,111 @@ impl Quaternion {
         Quaternion::from_real_imaginary((angle / 2.0).cos(), &(axis * (angle / 2.0).sin()))
     }
 
+    /// An orientation looking along `forward`, with the screen's up direction
+    /// as close to `up` as possible.
+    ///
+    /// This follows the conventions in `render::to_screen_space`, so the
+    /// rotated +x axis points along `forward`, +y is screen right, and +z
+    /// (forward cross right) is screen down. If `up` is parallel to `forward`
+    /// some perpendicular direction is used instead.
+    pub fn look_at(forward: R3, up: R3) -> Quaternion {
+        let x = forward.normalized();
+
+        let mut up_perp = up - x * dot(&up, &x);
+        if up_perp.norm() < 1e-9 {
+            // pick whichever axis is least aligned with forward
+            let fallback = if x.x.abs() < 0.9 {
+                R3::new(1.0, 0.0, 0.0)
+            } else {
+                R3::new(0.0, 1.0, 0.0)
+            };
+            up_perp = fallback - x * dot(&fallback, &x);
+        }
+
+        let z = -up_perp.normalized();
+        let y = cross(&z, &x);
+
+        Quaternion::from_rotation_matrix([[x.x, y.x, z.x], [x.y, y.y, z.y], [x.z, y.z, z.z]])
+    }
+
+    /// Build a quaternion from a rotation matrix indexed as `[row][column]`.
+    pub fn from_rotation_matrix(m: [[f64; 3]; 3]) -> Quaternion {
+        let trace = m[0][0] + m[1][1] + m[2][2];
+
+        // pick the largest component to divide by to keep things stable
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(
+                0.25 * s,
+                (m[2][1] - m[1][2]) / s,
+                (m[0][2] - m[2][0]) / s,
+                (m[1][0] - m[0][1]) / s,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            Quaternion::new(
+                (m[2][1] - m[1][2]) / s,
+                0.25 * s,
+                (m[0][1] + m[1][0]) / s,
+                (m[0][2] + m[2][0]) / s,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            Quaternion::new(
+                (m[0][2] - m[2][0]) / s,
+                (m[0][1] + m[1][0]) / s,
+                0.25 * s,
+                (m[1][2] + m[2][1]) / s,
+            )
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            Quaternion::new(
+                (m[1][0] - m[0][1]) / s,
+                (m[0][2] + m[2][0]) / s,
+                (m[1][2] + m[2][1]) / s,
+                0.25 * s,
+            )
+        };
+
+        q.normalized()
+    }
+
+    /// Build a rotation from Euler angles in radians using the ZYX convention:
+    /// yaw about z, then pitch about the new y, then roll about the new x.
+    pub fn from_euler(roll: f64, pitch: f64, yaw: f64) -> Quaternion {
+        let (sr, cr) = (roll * 0.5).sin_cos();
+        let (sp, cp) = (pitch * 0.5).sin_cos();
+        let (sy, cy) = (yaw * 0.5).sin_cos();
+
+        Quaternion {
+            r: cr * cp * cy + sr * sp * sy,
+            i: sr * cp * cy - cr * sp * sy,
+            j: cr * sp * cy + sr * cp * sy,
+            k: cr * cp * sy - sr * sp * cy,
+        }
+    }
+
+    /// The `(roll, pitch, yaw)` Euler angles of this rotation, see `from_euler`.
+    ///
+    /// At pitch = ±pi/2 roll and yaw are not unique, so roll is reported as 0
+    /// and all of the rotation about the vertical is put in yaw.
+    pub fn to_euler(self) -> (f64, f64, f64) {
+        let q = self.normalized();
+
+        let sin_pitch = (2.0 * (q.r * q.j - q.k * q.i)).clamp(-1.0, 1.0);
+        let pitch = sin_pitch.asin();
+
+        if sin_pitch.abs() > 1.0 - 1e-12 {
+            // gimbal lock
+            let yaw = -2.0 * sin_pitch.signum() * q.i.atan2(q.r);
+            (0.0, pitch, yaw)
+        } else {
+            let roll = (2.0 * (q.r * q.i + q.j * q.k)).atan2(1.0 - 2.0 * (q.i * q.i + q.j * q.j));
+            let yaw = (2.0 * (q.r * q.k + q.i * q.j)).atan2(1.0 - 2.0 * (q.j * q.j + q.k * q.k));
+            (roll, pitch, yaw)
+        }
+    }
+
     pub fn conjugate(&self) -> Quaternion {
         Quaternion {
             r: self.r,
@@ -70,6 +217,16 @@ impl Quaternion {
         }
     }
 
+    pub fn norm(&self) -> f64 {
+        (self.r * self.r + self.i * self.i + self.j * self.j + self.k * self.k).sqrt()
+    }
+
+    /// Scale to a unit quaternion to correct drift from repeated multiplication.
+    /// The zero quaternion has no direction, so normalizing it gives NaNs.
+    pub fn normalized(&self) -> Quaternion {
+        *self / self.norm()
+    }
+
     pub fn inverse(&self) -> Quaternion {
         let square_norm = self.r * self.r + self.i * self.i + self.j * self.j + self.k * self.k;
         self.conjugate() / square_norm
@@ -83,7 +240,245 @@ impl Quaternion {
         }
     }
 
+    /// The 4D dot product of two quaternions
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.r * other.r + self.i * other.i + self.j * other.j + self.k * other.k
+    }
+
+    /// Checks if each component is within `epsilon` of the other quaternion's.
+    /// Note that `q` and `-q` are the same rotation but are not approximately equal.
+    #[cfg(test)]
+    pub fn approx_eq(&self, other: &Quaternion, epsilon: f64) -> bool {
+        (self.r - other.r).abs() <= epsilon
+            && (self.i - other.i).abs() <= epsilon
+            && (self.j - other.j).abs() <= epsilon
+            && (self.k - other.k).abs() <= epsilon
+    }
+
+    /// Spherical linear interpolation between two orientations.
+    ///
+    /// Always takes the shortest path, so `other` may be negated. When the
+    /// orientations are very close this falls back to a normalized lerp to
+    /// avoid dividing by a tiny sine.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+        const LERP_THRESHOLD: f64 = 0.9995;
+
+        let start = self.normalized();
+        let mut end = other.normalized();
+
+        if t == 0.0 {
+            return start;
+        } else if t == 1.0 {
+            return end;
+        }
+
+        let mut cos_theta = start.dot(&end);
+        if cos_theta < 0.0 {
+            end = -end;
+            cos_theta = -cos_theta;
+        }
+
+        if cos_theta > LERP_THRESHOLD {
+            (start * (1.0 - t) + end * t).normalized()
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            start * (((1.0 - t) * theta).sin() / sin_theta) + end * ((t * theta).sin() / sin_theta)
+        }
+    }
+
+    /// The 3x3 rotation matrix for this rotation, indexed as `[row][column]`.
+    #[cfg(test)]
+    pub fn to_rotation_matrix(self) -> [[f64; 3]; 3] {
+        let Quaternion { r, i, j, k } = self.normalized();
+
+        [
+            [
+                1.0 - 2.0 * (j * j + k * k),
+                2.0 * (i * j - k * r),
+                2.0 * (i * k + j * r),
+            ],
+            [
+                2.0 * (i * j + k * r),
+                1.0 - 2.0 * (i * i + k * k),
+                2.0 * (j * k - i * r),
+            ],
+            [
+                2.0 * (i * k - j * r),
+                2.0 * (j * k + i * r),
+                1.0 - 2.0 * (i * i + j * j),
+            ],
+        ]
+    }
+
+    /// The unit axis and angle in radians of this rotation.
+    /// The identity rotation has no axis, so the x axis is returned with angle 0.
+    #[cfg(test)]
+    pub fn axis_angle(&self) -> (R3, f64) {
+        let q = self.normalized();
+        let imaginary = q.imaginary_component();
+        let sin_half = imaginary.norm();
+
+        if sin_half < 1e-12 {
+            (R3::new(1.0, 0.0, 0.0), 0.0)
+        } else {
+            (imaginary / sin_half, 2.0 * sin_half.atan2(q.r))
+        }
+    }
+
     pub fn rotate(&self, vec: &R3) -> R3 {
         (*self * Quaternion::from_real_imaginary(0.0, vec) * self.inverse()).imaginary_component()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn slerp_halfway_to_a_quarter_turn_is_an_eighth_turn() {
+        let start = Quaternion::zero_rotation();
+        let end = Quaternion::rotation(R3::UP, 0.5 * PI);
+
+        let (axis, angle) = start.slerp(&end, 0.5).axis_angle();
+        assert!(axis.approx_eq(&R3::UP, 1e-12));
+        assert!((angle - 0.25 * PI).abs() < 1e-12);
+
+        assert_eq!(start.slerp(&end, 0.0), start);
+        assert_eq!(start.slerp(&end, 1.0), end);
+    }
+
+    #[test]
+    fn slerp_takes_the_short_way_around() {
+        let start = Quaternion::zero_rotation();
+        let end = -Quaternion::rotation(R3::FORWARD, 0.5);
+
+        let (_, angle) = start.slerp(&end, 0.5).axis_angle();
+        assert!((angle - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn euler_angles_round_trip() {
+        let angles = [
+            (0.0, 0.0, 0.0),
+            (0.1, 0.2, 0.3),
+            (-1.2, 0.7, 2.5),
+            (3.0, -1.5, -3.0),
+            (0.5, 1.5, -0.25),
+        ];
+        for &(roll, pitch, yaw) in &angles {
+            let (r, p, y) = Quaternion::from_euler(roll, pitch, yaw).to_euler();
+            assert!((r - roll).abs() < 1e-9, "roll {} != {}", r, roll);
+            assert!((p - pitch).abs() < 1e-9, "pitch {} != {}", p, pitch);
+            assert!((y - yaw).abs() < 1e-9, "yaw {} != {}", y, yaw);
+        }
+    }
+
+    #[test]
+    fn euler_angles_at_gimbal_lock_give_the_same_rotation() {
+        let q = Quaternion::from_euler(0.3, 0.5 * PI, 0.8);
+        let (roll, pitch, yaw) = q.to_euler();
+        assert_eq!(roll, 0.0);
+        assert!((pitch - 0.5 * PI).abs() < 1e-9);
+
+        let v = R3::new(1.0, 2.0, 3.0);
+        let back = Quaternion::from_euler(roll, pitch, yaw);
+        assert!(back.rotate(&v).approx_eq(&q.rotate(&v), 1e-9));
+    }
+
+    fn some_rotations() -> Vec<Quaternion> {
+        vec![
+            Quaternion::rotation(R3::new(1.0, 2.0, 3.0).normalized(), 0.7),
+            Quaternion::rotation(R3::new(-1.0, 0.5, 0.0).normalized(), 2.9),
+            Quaternion::rotation(R3::UP, PI),
+            Quaternion::from_euler(0.4, -1.1, 2.2),
+        ]
+    }
+
+    #[test]
+    fn rotation_matrices_match_rotate() {
+        let vectors = [
+            R3::FORWARD,
+            R3::RIGHT,
+            R3::UP,
+            R3::new(0.3, -0.8, 0.5).normalized(),
+        ];
+        for q in some_rotations() {
+            let m = q.to_rotation_matrix();
+            for v in &vectors {
+                let by_matrix = R3::new(
+                    m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+                    m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+                    m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+                );
+                assert!(by_matrix.approx_eq(&q.rotate(v), 1e-12));
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_matrices_round_trip() {
+        for q in some_rotations() {
+            let back = Quaternion::from_rotation_matrix(q.to_rotation_matrix());
+            // q and -q are the same rotation
+            assert!(back.approx_eq(&q, 1e-12) || back.approx_eq(&-q, 1e-12));
+        }
+    }
+
+    #[test]
+    fn axis_angle_round_trips() {
+        for q in some_rotations() {
+            let (axis, angle) = q.axis_angle();
+            assert!((axis.norm() - 1.0).abs() < 1e-12);
+            assert!(Quaternion::rotation(axis, angle).approx_eq(&q, 1e-12));
+        }
+    }
+
+    #[test]
+    fn identity_has_a_unit_axis_and_no_angle() {
+        let (axis, angle) = Quaternion::zero_rotation().axis_angle();
+        assert_eq!(axis, R3::new(1.0, 0.0, 0.0));
+        assert_eq!(angle, 0.0);
+    }
+
+    /// The world direction of the top of the screen for a camera with this orientation
+    fn screen_up(q: &Quaternion) -> R3 {
+        q.rotate(&-R3::UP)
+    }
+
+    #[test]
+    fn look_at_points_forward_with_the_screen_up_towards_up() {
+        let cases = [
+            (R3::new(1.0, 2.0, 3.0), -R3::UP),
+            (R3::new(-4.0, 0.5, 0.0), R3::new(0.0, 1.0, 0.0)),
+            (R3::new(0.0, 0.0, 2.0), R3::new(1.0, 1.0, 0.0)),
+        ];
+        for (forward, up) in cases {
+            let q = Quaternion::look_at(forward, up);
+            let forward = forward.normalized();
+            assert!(q.rotate(&R3::FORWARD).approx_eq(&forward, 1e-12));
+
+            // up is only moved as far as it takes to be square with forward
+            let up_perp = (up - forward * dot(&up, &forward)).normalized();
+            assert!(screen_up(&q).approx_eq(&up_perp, 1e-12));
+        }
+    }
+
+    #[test]
+    fn look_at_along_up_still_points_forward() {
+        for forward in [
+            R3::new(0.0, 0.0, -3.0),
+            R3::new(0.0, 0.0, 3.0),
+            R3::new(2.0, 0.0, 0.0),
+        ] {
+            let q = Quaternion::look_at(forward, forward * 0.5);
+            let forward = forward.normalized();
+            assert!(q.rotate(&R3::FORWARD).approx_eq(&forward, 1e-12));
+            // some direction square with forward is chosen for up
+            let up = screen_up(&q);
+            assert!((up.norm() - 1.0).abs() < 1e-12);
+            assert!(dot(&up, &forward).abs() < 1e-12);
+        }
+    }
+}
@@ -1,6 +1,9 @@
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
 use std::ops;
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
 pub struct R3 {
     pub x: f64,
     pub y: f64,
@@ -57,6 +60,14 @@ impl ops::Sub for R3 {
     }
 }
 
+impl ops::SubAssign for R3 {
+    fn sub_assign(&mut self, rhs: R3) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+        self.z -= rhs.z;
+    }
+}
+
 impl ops::Mul<f64> for R3 {
     type Output = Self;
 
@@ -69,7 +80,89 @@ impl ops::Mul<f64> for R3 {
     }
 }
 
+impl ops::MulAssign<f64> for R3 {
+    fn mul_assign(&mut self, rhs: f64) {
+        self.x *= rhs;
+        self.y *= rhs;
+        self.z *= rhs;
+    }
+}
+
+impl ops::Div<f64> for R3 {
+    type Output = Self;
+
+    fn div(self, other: f64) -> Self::Output {
+        R3 {
+            x: self.x / other,
+            y: self.y / other,
+            z: self.z / other,
+        }
+    }
+}
+
+impl ops::DivAssign<f64> for R3 {
+    fn div_assign(&mut self, rhs: f64) {
+        self.x /= rhs;
+        self.y /= rhs;
+        self.z /= rhs;
+    }
+}
+
+impl From<[f64; 3]> for R3 {
+    fn from([x, y, z]: [f64; 3]) -> R3 {
+        R3 { x, y, z }
+    }
+}
+
+impl From<(f64, f64, f64)> for R3 {
+    fn from((x, y, z): (f64, f64, f64)) -> R3 {
+        R3 { x, y, z }
+    }
+}
+
+impl From<R3> for [f64; 3] {
+    fn from(v: R3) -> [f64; 3] {
+        [v.x, v.y, v.z]
+    }
+}
+
+/// Adds up vectors, starting from `R3::zero`. There is deliberately no
+/// `FromIterator`, since collecting many vectors into one reads like it
+/// should keep them all, rather than add them.
+impl std::iter::Sum for R3 {
+    fn sum<I: Iterator<Item = R3>>(iter: I) -> R3 {
+        iter.fold(R3::zero(), |acc, v| acc + v)
+    }
+}
+
+impl<'a> std::iter::Sum<&'a R3> for R3 {
+    fn sum<I: Iterator<Item = &'a R3>>(iter: I) -> R3 {
+        iter.copied().sum()
+    }
+}
+
 impl R3 {
+    /// Straight ahead for the camera and objects, see `render::to_screen_space`
+    pub const FORWARD: R3 = R3 {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    /// To the right of `FORWARD` on screen
+    pub const RIGHT: R3 = R3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    /// `FORWARD` cross `RIGHT`, completing the right handed camera frame.
+    /// Screen coordinates grow downwards, so this points down the screen and
+    /// `-UP` is the top of the screen.
+    pub const UP: R3 = R3 {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    };
+
     pub fn new(x: f64, y: f64, z: f64) -> R3 {
         R3 { x, y, z }
     }
@@ -83,16 +176,45 @@ impl R3 {
     }
 
     pub fn normalized(&self) -> R3 {
+        *self / self.norm()
+    }
+
+    pub fn norm(&self) -> f64 {
+        dot(self, self).sqrt()
+    }
+
+    pub fn distance(&self, other: &R3) -> f64 {
+        (*self - *other).norm()
+    }
+
+    /// The square of the distance, which avoids taking a square root
+    pub fn distance_squared(&self, other: &R3) -> f64 {
+        let diff = *self - *other;
+        dot(&diff, &diff)
+    }
+
+    /// Linear interpolation from `self` to `other`.
+    /// Values of `t` outside of [0, 1] extrapolate along the same line.
+    pub fn lerp(&self, other: &R3, t: f64) -> R3 {
+        *self + (*other - *self) * t
+    }
+
+    /// Scale the vector down so its norm is at most `max`. Shorter vectors,
+    /// including the zero vector, are returned unchanged.
+    pub fn clamp_length(&self, max: f64) -> R3 {
         let norm = self.norm();
-        R3 {
-            x: self.x / norm,
-            y: self.y / norm,
-            z: self.z / norm,
+        if norm > max {
+            *self * (max / norm)
+        } else {
+            *self
         }
     }
 
-    pub fn norm(&self) -> f64 {
-        dot(&self, &self).sqrt()
+    /// Checks if each component is within `epsilon` of the other vector's.
+    pub fn approx_eq(&self, other: &R3, epsilon: f64) -> bool {
+        (self.x - other.x).abs() <= epsilon
+            && (self.y - other.y).abs() <= epsilon
+            && (self.z - other.z).abs() <= epsilon
     }
 }
 
@@ -108,6 +230,15 @@ pub fn cross(a: &R3, b: &R3) -> R3 {
     }
 }
 
+/// Component-wise product of two vectors
+pub fn hadamard(a: &R3, b: &R3) -> R3 {
+    R3 {
+        x: a.x * b.x,
+        y: a.y * b.y,
+        z: a.z * b.z,
+    }
+}
+
 pub fn midpoint(a: &R3, b: &R3) -> R3 {
     R3 {
         x: (a.x + b.x) * 0.5,
@@ -115,3 +246,74 @@ pub fn midpoint(a: &R3, b: &R3) -> R3 {
         z: (a.z + b.z) * 0.5,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assigning_operators_match_the_plain_ones() {
+        let (a, b) = (R3::new(1.0, -2.0, 3.5), R3::new(0.5, 4.0, -1.0));
+
+        let mut scaled = a;
+        scaled *= 2.0;
+        assert_eq!(scaled, a * 2.0);
+
+        let mut divided = a;
+        divided /= 4.0;
+        assert_eq!(divided, a / 4.0);
+        assert_eq!(divided, a * 0.25);
+
+        let mut difference = a;
+        difference -= b;
+        assert_eq!(difference, a - b);
+        assert_eq!(difference + b, a);
+    }
+
+    #[test]
+    fn dividing_by_zero_gives_the_same_infinities_as_multiplying() {
+        let a = R3::new(1.0, -2.0, 3.5);
+        let infinite = R3::new(f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY);
+        assert_eq!(a / 0.0, infinite);
+        assert_eq!(a * f64::INFINITY, infinite);
+
+        let mut divided = a;
+        divided /= 0.0;
+        assert_eq!(divided, infinite);
+
+        // zero over zero has no sign to pick, just like zero times infinity
+        let nan = R3::zero() / 0.0;
+        assert!(nan.x.is_nan() && nan.y.is_nan() && nan.z.is_nan());
+        assert!((R3::zero() * f64::INFINITY).x.is_nan());
+    }
+
+    #[test]
+    fn converts_to_and_from_arrays_and_tuples() {
+        let v = R3::new(1.0, -2.0, 3.5);
+        assert_eq!(R3::from([1.0, -2.0, 3.5]), v);
+        assert_eq!(R3::from((1.0, -2.0, 3.5)), v);
+        assert_eq!(<[f64; 3]>::from(v), [1.0, -2.0, 3.5]);
+        assert_eq!(R3::from(<[f64; 3]>::from(v)), v);
+    }
+
+    #[test]
+    fn sums_vectors_from_zero() {
+        let vs = [
+            R3::new(1.0, 2.0, 3.0),
+            R3::new(-4.0, 0.5, 0.0),
+            R3::new(0.0, 0.0, 1.0),
+        ];
+        assert_eq!(vs.iter().sum::<R3>(), R3::new(-3.0, 2.5, 4.0));
+        assert_eq!(vs.iter().copied().sum::<R3>(), R3::new(-3.0, 2.5, 4.0));
+        assert_eq!(std::iter::empty::<R3>().sum::<R3>(), R3::zero());
+    }
+
+    #[test]
+    fn directions_follow_the_camera_convention() {
+        assert_eq!(R3::default(), R3::zero());
+        assert_eq!(cross(&R3::FORWARD, &R3::RIGHT), R3::UP);
+        // and the basis is right handed all the way round
+        assert_eq!(cross(&R3::RIGHT, &R3::UP), R3::FORWARD);
+        assert_eq!(cross(&R3::UP, &R3::FORWARD), R3::RIGHT);
+    }
+}
@@ -1,27 +1,190 @@
 use std::convert::TryInto;
+use std::fmt::Write;
+use std::fs;
 
-use gl;
 use graphics::Graphics;
 use graphics::Transformed;
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::mesh::{screen_mesh, world_vertices, Mesh};
 use super::quaternion::Quaternion;
+use super::r3::pose::Pose;
 use super::r3::*;
+use super::GameObject;
 
-#[derive(Copy, Clone, Debug)]
+/// Closest distance in front of the camera that perspective projection will draw
+pub const NEAR_PLANE: f64 = 0.01;
+
+/// How points in the world are mapped to the screen
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
+pub enum ProjectionMode {
+    /// Distance from the center of the screen is proportional to the angle
+    /// away from forward. This shows every direction, but bends straight lines.
+    Angular,
+    /// A pinhole camera that keeps lines straight. Points at `fov / 2` radians
+    /// from forward land where the angular projection would put them.
+    Perspective { fov: f64 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
 pub struct Camera {
     pub position: R3,
     pub orientation: Quaternion,
     pub scale: f64,
+    pub projection: ProjectionMode,
+}
+
+impl Camera {
+    /// Blend between two cameras, `t` of the way from `self` to `other`.
+    /// The scale and projection jump to `other`'s.
+    pub fn interpolate(&self, other: &Camera, t: f64) -> Camera {
+        Camera {
+            position: self.position.lerp(&other.position, t),
+            orientation: self.orientation.slerp(&other.orientation, t),
+            ..*other
+        }
+    }
+
+    /// The ray from the camera through a point on the screen, as an origin and
+    /// a unit direction. `screen` is relative to the center of the screen,
+    /// like the output of `to_screen_space`, which this inverts.
+    ///
+    /// With the angular projection this is only valid inside the circle of
+    /// radius `PI * scale`, where everything behind the camera wraps to. Points
+    /// outside it don't correspond to any direction. The perspective
+    /// projection is valid everywhere.
+    pub fn screen_to_ray(&self, screen: [f64; 2]) -> (R3, R3) {
+        let forward = self.orientation.rotate(&R3::FORWARD);
+        let right = self.orientation.rotate(&R3::RIGHT);
+        let down = cross(&forward, &right);
+
+        let direction = match self.projection {
+            ProjectionMode::Perspective { fov } => {
+                let half_fov = 0.5 * fov;
+                let focal_length = self.scale * half_fov / half_fov.tan();
+                (forward + right * (screen[0] / focal_length) + down * (screen[1] / focal_length))
+                    .normalized()
+            }
+            ProjectionMode::Angular => {
+                let radius = (screen[0].powi(2) + screen[1].powi(2)).sqrt();
+                if radius == 0.0 {
+                    forward
+                } else {
+                    // the distance from the center is the angle away from forward
+                    let alpha = radius / self.scale;
+                    let sideways = (right * screen[0] + down * screen[1]) / radius;
+                    forward * alpha.cos() + sideways * alpha.sin()
+                }
+            }
+        };
+
+        (self.position, direction)
+    }
+}
+
+/// Which parts of a mesh get drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenderStyle {
+    Wireframe,
+    Solid,
+    Both,
 }
 
-pub trait Renderable {
-    fn render(
-        &self,
-        c: &graphics::Context,
-        g: &mut opengl_graphics::GlGraphics,
-        camera: Camera,
-        center: graphics::math::Matrix2d,
-    );
+impl RenderStyle {
+    pub fn draws_lines(self) -> bool {
+        self != RenderStyle::Solid
+    }
+
+    /// Faces are much more expensive to draw than lines, since each one needs the stencil buffer
+    pub fn draws_faces(self) -> bool {
+        self != RenderStyle::Wireframe
+    }
+
+    /// The next style, for cycling through them with a key
+    pub fn next(self) -> RenderStyle {
+        match self {
+            RenderStyle::Both => RenderStyle::Wireframe,
+            RenderStyle::Wireframe => RenderStyle::Solid,
+            RenderStyle::Solid => RenderStyle::Both,
+        }
+    }
+}
+
+/// Fades colors towards `color` with distance from the camera
+#[derive(Copy, Clone, Debug)]
+pub struct Fog {
+    pub color: graphics::types::Color,
+    /// Distance where the fog begins
+    pub start: f64,
+    /// Distance where things are entirely fog colored
+    pub end: f64,
+}
+
+impl Fog {
+    /// Blend the color of something `distance` from the camera with the fog,
+    /// leaving its alpha alone
+    pub fn apply(&self, color: graphics::types::Color, distance: f64) -> graphics::types::Color {
+        let t = ((distance - self.start) / (self.end - self.start)).clamp(0.0, 1.0) as f32;
+        let blend = |c: f32, f: f32| c + (f - c) * t;
+        [
+            blend(color[0], self.color[0]),
+            blend(color[1], self.color[1]),
+            blend(color[2], self.color[2]),
+            color[3],
+        ]
+    }
+}
+
+/// Settings for how meshes are drawn
+#[derive(Copy, Clone, Debug)]
+pub struct RenderOptions {
+    /// Draw the approximation points along each curve
+    pub debug: bool,
+    /// Skip faces that point away from the camera
+    pub cull_backfaces: bool,
+    /// Curves are split until their points are this many pixels apart
+    pub resolution: f64,
+    /// The most times a curve can be split, see `approximate_curve`
+    pub max_split: i32,
+    /// Objects smaller than this many pixels across lose a split each time
+    /// their size halves, see `lod_max_split`
+    pub lod_falloff: f64,
+    /// Direction the light travels in, if faces should be shaded
+    pub light_dir: Option<R3>,
+    pub style: RenderStyle,
+    pub fog: Option<Fog>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            debug: false,
+            cull_backfaces: false,
+            resolution: 40.0,
+            max_split: 9,
+            lod_falloff: 400.0,
+            light_dir: None,
+            style: RenderStyle::Both,
+            fog: None,
+        }
+    }
+}
+
+/// Lambertian brightness of a surface with the given normal lit from `light_dir`
+pub fn lambert(normal: &R3, light_dir: &R3) -> f64 {
+    dot(&normal.normalized(), &-light_dir.normalized()).max(0.0)
+}
+
+/// Scale the brightness of a color, leaving its alpha alone
+pub fn shade(color: graphics::types::Color, brightness: f64) -> graphics::types::Color {
+    let b = brightness as f32;
+    [color[0] * b, color[1] * b, color[2] * b, color[3]]
 }
 
 /// The difference between two angles
@@ -40,11 +203,16 @@ pub fn angle_difference(start: f64, end: f64) -> f64 {
 
 /// Checks if a point is behind the camera
 fn is_behind(p: &R3, camera: &Camera) -> bool {
-    let forward = camera.orientation.rotate(&R3::new(1.0, 0.0, 0.0));
-    return dot(&(*p - camera.position), &forward) < 0.0;
+    let forward = camera.orientation.rotate(&R3::FORWARD);
+    dot(&(*p - camera.position), &forward) < 0.0
 }
 
-/// Push a set of points approximating a circle arc between start and end
+/// Push a set of points approximating a circle arc between start and end.
+///
+/// Only the points strictly between the ends are pushed, in order from start
+/// to end, no more than `CIRCLE_RES` radians apart from each other or from the
+/// ends. The caller already has the start point and pushes the end point
+/// itself, so the arc joins up with the segments on either side.
 fn approximate_circle<F>(
     start_x: f64,
     start_y: f64,
@@ -66,18 +234,12 @@ fn approximate_circle<F>(
     let step = angle_span / (count as f64);
     let radius_step = (end_radius - start_radius) / (count as f64);
 
-    // add each point
-    let mut i = 1;
-    let mut a = start_angle + step;
-    let mut r = start_radius + radius_step;
-    loop {
-        if i >= count {
-            break;
-        }
+    // add each point, computing from the start each time so rounding errors
+    // don't build up and leave a gap before the end point
+    for i in 1..count {
+        let a = start_angle + step * i as f64;
+        let r = start_radius + radius_step * i as f64;
         push_result([a.cos() * r, a.sin() * r]);
-        i += 1;
-        a += step;
-        r += radius_step;
     }
 
     // println!("angle_span: {:.2}, count: {}, step: {:.2}", angle_span, count, step);
@@ -142,6 +304,217 @@ pub fn approximate_curve(
     done.iter().map(|&x| x.1).collect()
 }
 
+/// How many times `approximate_curve` may split the line from a to b. Lines
+/// in front of the camera that cover a small angle, because they are short or
+/// far away, need fewer splits to get within `resolution` pixels. Lines
+/// reaching behind the camera can wrap around the screen, so they get the full
+/// `max_split`.
+pub fn split_budget(a: &R3, b: &R3, camera: &Camera, resolution: f64, max_split: i32) -> i32 {
+    if is_behind(a, camera) || is_behind(b, camera) {
+        return max_split;
+    }
+
+    let (to_a, to_b) = (*a - camera.position, *b - camera.position);
+    let cos_angle = dot(&to_a, &to_b) / (to_a.norm() * to_b.norm());
+    let pixels = camera.scale * cos_angle.clamp(-1.0, 1.0).acos();
+    if !pixels.is_finite() {
+        return max_split;
+    }
+
+    // each split halves the length, with one extra for the curvature
+    let splits = (pixels / resolution).log2().ceil().max(0.0) as i32 + 1;
+    splits.min(max_split)
+}
+
+/// The fewest splits `lod_max_split` will leave an object with
+pub const MIN_LOD_SPLIT: i32 = 2;
+
+/// The `max_split` for the lines of an object inside a sphere of `radius`
+/// that is `distance` from the camera. Objects that look small, because they
+/// are far away, get fewer splits, down to `MIN_LOD_SPLIT`. Objects the
+/// camera is in or near always get the full `options.max_split`.
+pub fn lod_max_split(radius: f64, distance: f64, camera: &Camera, options: &RenderOptions) -> i32 {
+    if distance <= 2.0 * radius {
+        return options.max_split;
+    }
+
+    let fewest = MIN_LOD_SPLIT.min(options.max_split);
+    let pixels = camera.scale * 2.0 * (radius / distance).asin();
+    let lost = (options.lod_falloff / pixels).log2().ceil().max(0.0);
+    if !lost.is_finite() {
+        return fewest;
+    }
+    (options.max_split - lost as i32).max(fewest)
+}
+
+/// Clips a line to the part in front of the near plane, or `None` if it is entirely behind it.
+pub fn clip_segment_near(a: R3, b: R3, camera: &Camera) -> Option<(R3, R3)> {
+    let forward = camera.orientation.rotate(&R3::FORWARD);
+    let depth_a = dot(&(a - camera.position), &forward) - NEAR_PLANE;
+    let depth_b = dot(&(b - camera.position), &forward) - NEAR_PLANE;
+
+    match (depth_a >= 0.0, depth_b >= 0.0) {
+        (true, true) => Some((a, b)),
+        (false, false) => None,
+        (true, false) => Some((a, a.lerp(&b, depth_a / (depth_a - depth_b)))),
+        (false, true) => Some((a.lerp(&b, depth_a / (depth_a - depth_b)), b)),
+    }
+}
+
+/// Projects a line in R3 that stays straight on screen, as in perspective
+/// projection. The part of the line behind the near plane is clipped off, so
+/// this is empty if the whole line is behind the camera.
+pub fn project_segment(a: &R3, b: &R3, camera: Camera) -> Vec<[f64; 2]> {
+    match clip_segment_near(*a, *b, &camera) {
+        Some((a, b)) => vec![to_screen_space(&a, &camera), to_screen_space(&b, &camera)],
+        None => Vec::new(),
+    }
+}
+
+/// Projects a line in R3 however the camera's projection needs, approximating
+/// its curve under the angular projection, see `approximate_curve`.
+pub fn project_line(
+    a: &R3,
+    b: &R3,
+    camera: Camera,
+    resolution: f64,
+    max_split: i32,
+) -> Vec<[f64; 2]> {
+    match camera.projection {
+        ProjectionMode::Angular => {
+            let splits = split_budget(a, b, &camera, resolution, max_split);
+            approximate_curve(a, b, camera, resolution, splits)
+        }
+        // straight lines stay straight, so there is nothing to approximate
+        ProjectionMode::Perspective { .. } => project_segment(a, b, camera),
+    }
+}
+
+/// The screen space curve of every edge of a mesh whose vertices have already
+/// been moved into world space, in the same order as `mesh.edges`
+pub fn project_edges(
+    mesh: &Mesh,
+    transformed_vertices: &[R3],
+    camera: Camera,
+    resolution: f64,
+    max_split: i32,
+) -> Vec<Vec<[f64; 2]>> {
+    // approximating curves doesn't touch the GL state, so it can be done in parallel
+    #[cfg(feature = "rayon")]
+    let edges = mesh.edges.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let edges = mesh.edges.iter();
+
+    edges
+        .map(|(ai, bi)| {
+            project_line(
+                &transformed_vertices[*ai],
+                &transformed_vertices[*bi],
+                camera,
+                resolution,
+                max_split,
+            )
+        })
+        .collect()
+}
+
+/// The screen space curves of a mesh's lines and their colors, without
+/// drawing anything. Lines entirely behind a perspective camera come out empty.
+pub fn project_mesh(
+    mesh: &Mesh,
+    pose: &Pose,
+    camera: Camera,
+    resolution: f64,
+    max_split: i32,
+) -> Vec<(graphics::types::Color, Vec<[f64; 2]>)> {
+    let vertices = world_vertices(mesh, pose);
+    let curves = project_edges(mesh, &vertices, camera, resolution, max_split);
+    mesh.lines
+        .iter()
+        .map(|(ci, color)| (*color, curves[*ci].clone()))
+        .collect()
+}
+
+/// Draw a reference grid that is already in world space, see `mesh::grid`
+pub fn render_grid(
+    grid: &Mesh,
+    options: &RenderOptions,
+    camera: Camera,
+    c: &graphics::Context,
+    g: &mut opengl_graphics::GlGraphics,
+    center: graphics::math::Matrix2d,
+) {
+    let world = Pose {
+        pos: R3::zero(),
+        orientation: Quaternion::zero_rotation(),
+    };
+    let curves = project_mesh(grid, &world, camera, options.resolution, options.max_split);
+    for (color, points) in &curves {
+        render_curve(*color, points, options.debug, c, g, center);
+    }
+}
+
+/// The far ends of the x, y and z axes drawn by `render_axes`, in red, green
+/// and blue
+pub fn axis_ends(length: f64) -> [(R3, graphics::types::Color); 3] {
+    [
+        (R3::new(length, 0.0, 0.0), [1.0, 0.0, 0.0, 1.0]),
+        (R3::new(0.0, length, 0.0), [0.0, 1.0, 0.0, 1.0]),
+        (R3::new(0.0, 0.0, length), [0.0, 0.0, 1.0, 1.0]),
+    ]
+}
+
+/// Draw the world axes out from the origin, see `axis_ends`
+pub fn render_axes(
+    length: f64,
+    options: &RenderOptions,
+    camera: Camera,
+    c: &graphics::Context,
+    g: &mut opengl_graphics::GlGraphics,
+    center: graphics::math::Matrix2d,
+) {
+    for (end, color) in axis_ends(length) {
+        let points = project_line(
+            &R3::zero(),
+            &end,
+            camera,
+            options.resolution,
+            options.max_split,
+        );
+        render_curve(color, &points, options.debug, c, g, center);
+    }
+}
+
+/// The x, y and z axes of an object's local space drawn out from its
+/// position, as start, end and color, see `axis_ends`
+pub fn object_axes(obj: &GameObject, length: f64) -> [(R3, R3, graphics::types::Color); 3] {
+    axis_ends(length).map(|(end, color)| {
+        let pos = obj.pose.pos;
+        (pos, obj.pose.orientation.rotate(&end) + pos, color)
+    })
+}
+
+/// Draw an object's local axes, reaching a bit past its bounding sphere, and
+/// where its velocity will take it in the next second
+pub fn render_object_gizmo(
+    obj: &GameObject,
+    options: &RenderOptions,
+    camera: Camera,
+    c: &graphics::Context,
+    g: &mut opengl_graphics::GlGraphics,
+    center: graphics::math::Matrix2d,
+) {
+    const VELOCITY: graphics::types::Color = [1.0, 1.0, 1.0, 1.0];
+
+    let mut lines = object_axes(obj, 1.5 * obj.bounding_radius).to_vec();
+    lines.push((obj.pose.pos, obj.pose.pos + obj.velocity, VELOCITY));
+    for (start, end, color) in lines {
+        let points = project_line(&start, &end, camera, options.resolution, options.max_split);
+        // dots along these would just clutter the object's own
+        render_curve(color, &points, false, c, g, center);
+    }
+}
+
 pub fn render_curve(
     color: graphics::types::Color,
     points: &[[f64; 2]],
@@ -150,8 +523,8 @@ pub fn render_curve(
     g: &mut opengl_graphics::GlGraphics,
     center: graphics::math::Matrix2d,
 ) {
-    match points.get(0) {
-        None => return,
+    match points.first() {
+        None => (),
 
         Some(start) => {
             let line = graphics::Line::new(color, 1.0);
@@ -165,15 +538,13 @@ pub fn render_curve(
                     g,
                 );
                 // debug dots
-                if debug {
-                    if i != points.len() - 1 {
-                        graphics::Ellipse::new([1.0, 1.0, 1.0, 0.5]).draw(
-                            graphics::ellipse::circle(0.0, 0.0, 2.0),
-                            &c.draw_state,
-                            center.trans(next[0], next[1]),
-                            g,
-                        );
-                    }
+                if debug && i != points.len() - 1 {
+                    graphics::Ellipse::new([1.0, 1.0, 1.0, 0.5]).draw(
+                        graphics::ellipse::circle(0.0, 0.0, 2.0),
+                        &c.draw_state,
+                        center.trans(next[0], next[1]),
+                        g,
+                    );
                 }
                 prev = next;
             }
@@ -181,24 +552,73 @@ pub fn render_curve(
     }
 }
 
-fn flush_graphics(transform: graphics::math::Matrix2d, g: &mut opengl_graphics::GlGraphics) {
+/// A rectangle covering the whole viewport, in coordinates centered on the screen
+pub fn screen_rect(view_size: [f64; 2]) -> [f64; 4] {
+    let [w, h] = view_size;
+    [-0.5 * w, -0.5 * h, w, h]
+}
+
+/// Whether any of a sphere in world space could land on a screen of
+/// `view_size` pixels. This is conservative, some spheres it lets through
+/// still end up off screen.
+///
+/// Both projections put a point farther from the middle of the screen the
+/// farther it is from forward, so the sphere is off screen if its nearest
+/// direction is past the angle of the screen's corners. Spheres the camera is
+/// inside of cover every direction and are always drawn.
+pub fn sphere_on_screen(center: &R3, radius: f64, camera: &Camera, view_size: [f64; 2]) -> bool {
+    let to_center = *center - camera.position;
+    let distance = to_center.norm();
+    if distance <= radius {
+        return true;
+    }
+
+    let forward = camera.orientation.rotate(&R3::FORWARD);
+    let angle = dot(&(to_center / distance), &forward)
+        .clamp(-1.0, 1.0)
+        .acos();
+    let angular_radius = (radius / distance).asin();
+
+    let corner = 0.5 * view_size[0].hypot(view_size[1]);
+    let max_angle = match camera.projection {
+        ProjectionMode::Angular => corner / camera.scale,
+        ProjectionMode::Perspective { fov } => {
+            let half_fov = 0.5 * fov;
+            let focal_length = camera.scale * half_fov / half_fov.tan();
+            (corner / focal_length).atan()
+        }
+    };
+
+    angle - angular_radius <= max_angle
+}
+
+fn flush_graphics(
+    screen: [f64; 4],
+    transform: graphics::math::Matrix2d,
+    g: &mut opengl_graphics::GlGraphics,
+) {
     let color = [0.0, 0.0, 0.0, 1.0];
-    let rect = [-540.0, -540.0, 1.0, 1.0];
+    let rect = [screen[0], screen[1], 1.0, 1.0];
     graphics::Rectangle::new(color).draw(rect, &graphics::DrawState::default(), transform, g);
     g.clear_draw_state();
     graphics::Rectangle::new(color).draw(rect, &graphics::DrawState::default(), transform, g);
 }
 
+/// Fill a polygon centered on the screen. If it `is_behind` the camera the
+/// polygon is inverted within the circle everything behind the camera wraps to.
 pub fn draw_poly(
     color: graphics::types::Color,
     poly: &[[f64; 2]],
     is_behind: bool,
-    _draw_state: &graphics::DrawState,
+    camera: &Camera,
+    context: &graphics::Context,
     transform: graphics::math::Matrix2d,
     g: &mut opengl_graphics::GlGraphics,
 ) {
+    let screen = screen_rect(context.get_view_size());
+
     // flush any old graphics before manually messing with the draw state
-    flush_graphics(transform, g);
+    flush_graphics(screen, transform, g);
 
     // cannot set blend to invert on the clip draw state
     let clip = graphics::DrawState::new_clip();
@@ -218,22 +638,18 @@ pub fn draw_poly(
 
     if is_behind {
         // invert the stencil
+        let radius = camera.scale * std::f64::consts::PI;
         graphics::Ellipse::new([1.0, 1.0, 1.0, 1.0]).draw(
-            graphics::rectangle::square(-540.0, -540.0, 1080.0),
+            graphics::rectangle::square(-radius, -radius, 2.0 * radius),
             &clip,
             transform,
             g,
         );
     }
 
-    graphics::Rectangle::new(color).draw(
-        [-540.0, -540.0, 1080.0, 1080.0],
-        &graphics::DrawState::new_inside(),
-        transform,
-        g,
-    );
+    graphics::Rectangle::new(color).draw(screen, &graphics::DrawState::new_inside(), transform, g);
 
-    flush_graphics(transform, g);
+    flush_graphics(screen, transform, g);
 
     // debug points
     // for (n, p) in poly.iter().enumerate() {
@@ -243,19 +659,152 @@ pub fn draw_poly(
     // }
 }
 
+/// Write the objects, as the camera sees them on a screen of `size` pixels,
+/// to an svg file. Everything is projected, shaded and culled the same way
+/// `render_mesh` does with the same `options`.
+pub fn export_svg(
+    objects: &[GameObject],
+    camera: Camera,
+    size: [f64; 2],
+    options: &RenderOptions,
+    path: &str,
+) -> Result<(), String> {
+    fs::write(path, svg(objects, camera, size, options))
+        .map_err(|e| format!("Could not write {}: {}", path, e))
+}
+
+/// The svg document written by `export_svg`, with a path per face and a polyline per line
+pub fn svg(
+    objects: &[GameObject],
+    camera: Camera,
+    size: [f64; 2],
+    options: &RenderOptions,
+) -> String {
+    let [w, h] = size;
+    let to_svg = |points: &[[f64; 2]]| -> Vec<String> {
+        points
+            .iter()
+            .map(|[x, y]| format!("{:.2},{:.2}", x + 0.5 * w, y + 0.5 * h))
+            .collect()
+    };
+    let rgb = |color: graphics::types::Color| {
+        let [r, g, b, a] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        (format!("rgb({},{},{})", r, g, b), a as f64 / 255.0)
+    };
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        w, h, w, h
+    )
+    .unwrap();
+    writeln!(out, r#"<rect width="100%" height="100%" fill="black"/>"#).unwrap();
+
+    for obj in objects.iter().filter(|obj| obj.should_draw(&camera, size)) {
+        let vertices = match &obj.world_vertices {
+            Some(vertices) => vertices.clone(),
+            None => world_vertices(&obj.mesh, &obj.pose),
+        };
+        let screen = screen_mesh(&obj.mesh, &vertices, options, None, camera);
+
+        for (color, points) in &screen.lines {
+            if points.len() < 2 {
+                continue;
+            }
+            let (stroke, opacity) = rgb(*color);
+            writeln!(
+                out,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-opacity="{:.3}"/>"#,
+                to_svg(points).join(" "),
+                stroke,
+                opacity
+            )
+            .unwrap();
+        }
+
+        for (color, points, is_behind) in &screen.faces {
+            let mut d = format!("M{}Z", to_svg(points).join("L"));
+            if *is_behind {
+                // even-odd filling against a circle around everything inverts
+                // the face, like the stencil in `draw_poly`
+                let radius = camera.scale * std::f64::consts::PI;
+                let (cx, cy) = (0.5 * w, 0.5 * h);
+                write!(
+                    d,
+                    "M{:.2},{:.2}A{r:.2},{r:.2} 0 1,0 {:.2},{:.2}A{r:.2},{r:.2} 0 1,0 {:.2},{:.2}Z",
+                    cx - radius,
+                    cy,
+                    cx + radius,
+                    cy,
+                    cx - radius,
+                    cy,
+                    r = radius
+                )
+                .unwrap();
+            }
+            let (fill, opacity) = rgb(*color);
+            writeln!(
+                out,
+                r#"<path d="{}" fill="{}" fill-opacity="{:.3}" fill-rule="evenodd"/>"#,
+                d, fill, opacity
+            )
+            .unwrap();
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Turn rows of RGBA pixels read from GL, which start at the bottom of the
+/// screen, into an image that starts at the top. `None` if there aren't
+/// exactly enough pixels for the size.
+#[cfg(feature = "image")]
+pub fn image_from_gl_pixels(width: u32, height: u32, pixels: &[u8]) -> Option<image::RgbaImage> {
+    let row = 4 * width as usize;
+    if pixels.len() != row * height as usize {
+        return None;
+    }
+    let flipped = pixels.chunks_exact(row).rev().flatten().copied().collect();
+    image::RgbaImage::from_raw(width, height, flipped)
+}
+
+/// Read back what has been drawn so far this frame. The size should be the
+/// viewport's size in pixels, `RenderArgs::draw_size`.
+#[cfg(feature = "image")]
+pub fn capture_screenshot(width: u32, height: u32) -> image::RgbaImage {
+    let mut pixels = vec![0u8; 4 * width as usize * height as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+    }
+    image_from_gl_pixels(width, height, &pixels).expect("the buffer fits the screen")
+}
+
 pub fn to_screen_space(point: &R3, camera: &Camera) -> [f64; 2] {
     let to_point = *point - camera.position;
 
-    let forward = camera.orientation.rotate(&R3 {
-        x: 1.0,
-        y: 0.0,
-        z: 0.0,
-    });
-    let right = camera.orientation.rotate(&R3 {
-        x: 0.0,
-        y: 1.0,
-        z: 0.0,
-    });
+    let forward = camera.orientation.rotate(&R3::FORWARD);
+    let right = camera.orientation.rotate(&R3::RIGHT);
+
+    if let ProjectionMode::Perspective { fov } = camera.projection {
+        // points behind the near plane should be clipped before they get here
+        let depth = dot(&to_point, &forward).max(NEAR_PLANE);
+        let half_fov = 0.5 * fov;
+        let focal_length = camera.scale * half_fov / half_fov.tan();
+        let x = dot(&to_point, &right) / depth;
+        let y = dot(&to_point, &cross(&forward, &right)) / depth;
+        return [focal_length * x, focal_length * y];
+    }
 
     let alpha = dot(&to_point.normalized(), &forward).acos();
 
@@ -271,3 +820,387 @@ pub fn to_screen_space(point: &R3, camera: &Camera) -> [f64; 2] {
         [camera.scale * x, camera.scale * y]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::cuboid;
+
+    fn perspective_camera() -> Camera {
+        Camera {
+            position: R3::zero(),
+            orientation: Quaternion::zero_rotation(),
+            scale: 500.0,
+            projection: ProjectionMode::Perspective { fov: 1.2 },
+        }
+    }
+
+    #[test]
+    fn project_mesh_puts_cuboid_corners_where_the_camera_sees_them() {
+        let mesh = cuboid(R3::new(2.0, 4.0, 6.0), [1.0; 4]);
+        let pose = Pose {
+            pos: R3::new(20.0, 1.0, -2.0),
+            orientation: Quaternion::rotation(R3::new(1.0, 2.0, 3.0).normalized(), 0.4),
+        };
+        let camera = perspective_camera();
+
+        let projected = project_mesh(&mesh, &pose, camera, 40.0, 9);
+        assert_eq!(projected.len(), 12);
+
+        let vertices = world_vertices(&mesh, &pose);
+        for ((color, points), (ci, _)) in projected.iter().zip(&mesh.lines) {
+            let (a, b) = mesh.edges[*ci];
+            let expected = [
+                to_screen_space(&vertices[a], &camera),
+                to_screen_space(&vertices[b], &camera),
+            ];
+            assert_eq!(*color, [1.0; 4]);
+            assert_eq!(points.len(), 2);
+            for (p, e) in points.iter().zip(&expected) {
+                assert!((p[0] - e[0]).abs() < 1e-9 && (p[1] - e[1]).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn project_mesh_leaves_lines_behind_the_camera_empty() {
+        let mesh = cuboid(R3::new(2.0, 2.0, 2.0), [1.0; 4]);
+        let pose = Pose {
+            pos: R3::new(-20.0, 0.0, 0.0),
+            orientation: Quaternion::zero_rotation(),
+        };
+
+        let projected = project_mesh(&mesh, &pose, perspective_camera(), 40.0, 9);
+        assert_eq!(projected.len(), 12);
+        assert!(projected.iter().all(|(_, points)| points.is_empty()));
+    }
+
+    #[test]
+    fn screen_to_ray_points_back_at_what_was_projected() {
+        let points = [
+            R3::new(30.0, 4.0, -7.0),
+            R3::new(5.0, -3.0, 2.0),
+            R3::new(2.0, 8.0, 9.0),
+            R3::new(-4.0, 1.0, 1.0),
+        ];
+        let perspective = Camera {
+            position: R3::new(1.0, -2.0, 0.5),
+            orientation: Quaternion::rotation(R3::new(0.0, 1.0, 1.0).normalized(), 0.3),
+            ..perspective_camera()
+        };
+        let angular = Camera {
+            projection: ProjectionMode::Angular,
+            ..perspective
+        };
+
+        for camera in &[perspective, angular] {
+            let forward = camera.orientation.rotate(&R3::FORWARD);
+            for point in &points {
+                let to_point = *point - camera.position;
+                if camera.projection != ProjectionMode::Angular && dot(&to_point, &forward) <= 0.0 {
+                    // only the angular projection sees behind the camera
+                    continue;
+                }
+
+                let (origin, direction) = camera.screen_to_ray(to_screen_space(point, camera));
+                assert_eq!(origin, camera.position);
+                assert!((direction.norm() - 1.0).abs() < 1e-9);
+                assert!(direction.approx_eq(&to_point.normalized(), 1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn object_axes_are_orthogonal_and_start_at_the_object() {
+        let mut obj = object_at(R3::new(4.0, -1.0, 2.0));
+        obj.pose.orientation = Quaternion::rotation(R3::new(1.0, -2.0, 0.5).normalized(), 1.1);
+
+        let axes = object_axes(&obj, 3.0);
+        let directions: Vec<R3> = axes.iter().map(|(start, end, _)| *end - *start).collect();
+        for (start, _, _) in &axes {
+            assert_eq!(*start, obj.pose.pos);
+        }
+        for (i, a) in directions.iter().enumerate() {
+            assert!((a.norm() - 3.0).abs() < 1e-12);
+            for b in &directions[i + 1..] {
+                assert!(dot(a, b).abs() < 1e-12);
+            }
+        }
+        // still right handed after rotating
+        assert!(cross(&directions[0], &directions[1]).approx_eq(&(directions[2] * 3.0), 1e-12));
+        assert_eq!(
+            axes.iter().map(|(_, _, color)| *color).collect::<Vec<_>>(),
+            vec![
+                [1.0, 0.0, 0.0, 1.0],
+                [0.0, 1.0, 0.0, 1.0],
+                [0.0, 0.0, 1.0, 1.0]
+            ]
+        );
+    }
+
+    fn object_at(pos: R3) -> GameObject {
+        crate::tests::object(cuboid(R3::new(2.0, 2.0, 2.0), [1.0, 0.5, 0.0, 1.0]), pos)
+    }
+
+    #[test]
+    fn svg_follows_the_render_options() {
+        let objects = [object_at(R3::new(20.0, 0.0, 0.0))];
+        let camera = perspective_camera();
+        let size = [800.0, 600.0];
+
+        let wireframe = RenderOptions {
+            style: RenderStyle::Wireframe,
+            ..RenderOptions::default()
+        };
+        let out = svg(&objects, camera, size, &wireframe);
+        assert_eq!(out.matches("<polyline").count(), 12);
+        assert_eq!(out.matches("<path").count(), 0);
+
+        let solid = RenderOptions {
+            style: RenderStyle::Solid,
+            cull_backfaces: true,
+            ..RenderOptions::default()
+        };
+        let out = svg(&objects, camera, size, &solid);
+        assert_eq!(out.matches("<polyline").count(), 0);
+        // only the face towards the camera survives culling
+        assert_eq!(out.matches("<path").count(), 1);
+        assert!(out.contains(r#"fill="rgb(255,128,0)""#));
+    }
+
+    #[test]
+    fn svg_leaves_out_objects_off_screen() {
+        let objects = [object_at(R3::new(-20.0, 0.0, 0.0))];
+        let out = svg(
+            &objects,
+            perspective_camera(),
+            [800.0, 600.0],
+            &RenderOptions::default(),
+        );
+        assert!(!out.contains("<polyline"));
+    }
+
+    #[test]
+    fn spheres_behind_or_beside_the_camera_are_off_screen() {
+        let camera = perspective_camera();
+        let size = [800.0, 600.0];
+        assert!(sphere_on_screen(
+            &R3::new(20.0, 0.0, 0.0),
+            1.0,
+            &camera,
+            size
+        ));
+        assert!(!sphere_on_screen(
+            &R3::new(-20.0, 0.0, 0.0),
+            1.0,
+            &camera,
+            size
+        ));
+        assert!(!sphere_on_screen(
+            &R3::new(0.0, 20.0, 0.0),
+            1.0,
+            &camera,
+            size
+        ));
+        // the camera is inside this one, so it covers the whole screen
+        assert!(sphere_on_screen(
+            &R3::new(-2.0, 0.0, 0.0),
+            3.0,
+            &camera,
+            size
+        ));
+    }
+
+    #[test]
+    fn segments_are_clipped_to_the_near_plane() {
+        let camera = Camera {
+            position: R3::new(1.0, 2.0, 3.0),
+            orientation: Quaternion::rotation(R3::new(1.0, -1.0, 2.0).normalized(), 0.7),
+            ..perspective_camera()
+        };
+        let forward = camera.orientation.rotate(&R3::FORWARD);
+        let right = camera.orientation.rotate(&R3::RIGHT);
+        let at = |depth: f64, across: f64| camera.position + forward * depth + right * across;
+
+        let (front, far) = (at(5.0, 1.0), at(8.0, -2.0));
+        assert_eq!(clip_segment_near(front, far, &camera), Some((front, far)));
+
+        let (behind, further) = (at(-1.0, 1.0), at(-4.0, -2.0));
+        assert_eq!(clip_segment_near(behind, further, &camera), None);
+
+        // either way round, the part behind the plane is cut off at the plane
+        let (a, b) = clip_segment_near(behind, front, &camera).unwrap();
+        assert_eq!(b, front);
+        assert!((dot(&(a - camera.position), &forward) - NEAR_PLANE).abs() < 1e-12);
+        let (c, d) = clip_segment_near(front, behind, &camera).unwrap();
+        assert_eq!(c, front);
+        assert!(d.approx_eq(&a, 1e-12));
+    }
+
+    #[test]
+    fn screen_rect_covers_the_whole_viewport() {
+        let [x, y, w, h] = screen_rect([1280.0, 720.0]);
+        assert_eq!([x, y, w, h], [-640.0, -360.0, 1280.0, 720.0]);
+        // drawn from the middle of the screen it reaches every edge
+        let (center_x, center_y) = (640.0, 360.0);
+        assert_eq!([center_x + x, center_y + y], [0.0, 0.0]);
+        assert_eq!([center_x + x + w, center_y + y + h], [1280.0, 720.0]);
+    }
+
+    #[test]
+    fn parallel_projection_matches_one_at_a_time() {
+        // big enough that rayon splits the work up
+        let mesh = crate::mesh::uv_sphere(5.0, 40, 60, [1.0; 4]);
+        let pose = Pose {
+            pos: R3::new(30.0, 2.0, -1.0),
+            orientation: Quaternion::rotation(R3::new(1.0, 2.0, 3.0).normalized(), 0.4),
+        };
+        let camera = Camera {
+            projection: ProjectionMode::Angular,
+            ..perspective_camera()
+        };
+
+        let vertices = world_vertices(&mesh, &pose);
+        let serial: Vec<R3> = mesh
+            .vertices
+            .iter()
+            .map(|v| pose.orientation.rotate(v) + pose.pos)
+            .collect();
+        assert_eq!(vertices, serial);
+
+        let curves = project_edges(&mesh, &vertices, camera, 10.0, 6);
+        assert_eq!(curves.len(), mesh.edges.len());
+        for (&(a, b), curve) in mesh.edges.iter().zip(&curves) {
+            assert_eq!(
+                *curve,
+                project_line(&serial[a], &serial[b], camera, 10.0, 6)
+            );
+        }
+    }
+
+    #[test]
+    fn render_styles_cycle_through_every_combination() {
+        let styles = [
+            RenderStyle::Both,
+            RenderStyle::Wireframe,
+            RenderStyle::Solid,
+        ];
+        for (i, style) in styles.iter().enumerate() {
+            assert_eq!(style.next(), styles[(i + 1) % styles.len()]);
+        }
+        let drawn: Vec<(bool, bool)> = styles
+            .iter()
+            .map(|style| (style.draws_lines(), style.draws_faces()))
+            .collect();
+        assert_eq!(drawn, vec![(true, true), (true, false), (false, true)]);
+    }
+
+    #[test]
+    fn wider_lines_get_more_splits() {
+        let camera = perspective_camera();
+        let a = R3::new(100.0, 0.0, 0.0);
+        let budget = |b: R3, max_split| split_budget(&a, &b, &camera, 40.0, max_split);
+
+        // 5 pixels long only needs the one split for curvature
+        assert_eq!(budget(R3::new(100.0, 1.0, 0.0), 9), 1);
+        // an eighth of a turn covers about 390 pixels, which takes 4 halvings
+        assert_eq!(budget(R3::new(100.0, 100.0, 0.0), 9), 5);
+        assert_eq!(budget(R3::new(0.0, 100.0, 0.0), 9), 6);
+        assert_eq!(budget(R3::new(100.0, 100.0, 0.0), 3), 3);
+
+        // anything reaching behind the camera might wrap around the screen
+        assert_eq!(budget(R3::new(-5.0, 1.0, 0.0), 9), 9);
+    }
+
+    /// The points `approximate_circle` puts between two ends, with the ends included
+    fn arc(start: [f64; 2], end: [f64; 2]) -> Vec<[f64; 2]> {
+        let mut points = vec![start];
+        approximate_circle(start[0], start[1], end[0], end[1], |p| points.push(p));
+        points.push(end);
+        points
+    }
+
+    #[test]
+    fn circle_arcs_run_from_start_to_end_in_small_steps() {
+        let points = arc([100.0, 0.0], [0.0, 50.0]);
+        // a quarter turn in steps of at most 0.1 radians
+        assert_eq!(points.len(), 17);
+
+        let angles: Vec<f64> = points.iter().map(|p| p[1].atan2(p[0])).collect();
+        let radii: Vec<f64> = points.iter().map(|p| p[0].hypot(p[1])).collect();
+        for i in 1..points.len() {
+            // including the last step onto the end point
+            let step = angles[i] - angles[i - 1];
+            assert!(step > 0.0 && step <= 0.1 + 1e-12);
+            assert!(radii[i] < radii[i - 1]);
+        }
+    }
+
+    #[test]
+    fn circle_arcs_take_the_short_way_around() {
+        // either side of the negative x axis, where atan2 jumps
+        let points = arc([-100.0, 1.0], [-100.0, -1.0]);
+        assert!(points.iter().all(|p| p[0] < -99.0));
+
+        // the ends are all there is when they are the same point
+        assert_eq!(arc([3.0, 4.0], [3.0, 4.0]).len(), 2);
+    }
+
+    #[test]
+    fn fog_thickens_from_its_start_to_its_end() {
+        let fog = Fog {
+            color: [0.0, 0.0, 0.0, 1.0],
+            start: 50.0,
+            end: 150.0,
+        };
+        let color = [1.0, 0.5, 0.25, 0.5];
+
+        assert_eq!(fog.apply(color, 10.0), color);
+        assert_eq!(fog.apply(color, 50.0), color);
+        assert_eq!(fog.apply(color, 100.0), [0.5, 0.25, 0.125, 0.5]);
+        // alpha is kept even once only the fog shows
+        assert_eq!(fog.apply(color, 150.0), [0.0, 0.0, 0.0, 0.5]);
+        assert_eq!(fog.apply(color, 1000.0), [0.0, 0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn lod_splits_drop_with_distance() {
+        let camera = perspective_camera();
+        let options = RenderOptions::default();
+        let splits: Vec<i32> = (1..200)
+            .map(|d| lod_max_split(1.0, d as f64, &camera, &options))
+            .collect();
+
+        assert_eq!(splits[0], options.max_split);
+        assert_eq!(*splits.last().unwrap(), MIN_LOD_SPLIT);
+        assert!(splits.windows(2).all(|w| w[1] <= w[0]));
+
+        // a larger falloff loses detail sooner
+        let sharper = RenderOptions {
+            lod_falloff: 2.0 * options.lod_falloff,
+            ..options
+        };
+        assert!(lod_max_split(1.0, 20.0, &camera, &sharper) < splits[19]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn gl_pixels_are_flipped_into_a_png() {
+        // the bottom row comes first from GL
+        let bottom = [1, 2, 3, 255, 4, 5, 6, 255];
+        let top = [7, 8, 9, 255, 10, 11, 12, 255];
+        let pixels: Vec<u8> = bottom.iter().chain(&top).copied().collect();
+
+        let image = image_from_gl_pixels(2, 2, &pixels).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [7, 8, 9, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [10, 11, 12, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [1, 2, 3, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [4, 5, 6, 255]);
+        assert!(image_from_gl_pixels(2, 2, &pixels[1..]).is_none());
+
+        let path = std::env::temp_dir().join("fieldgame-flipped.png");
+        image.save(&path).unwrap();
+        let decoded = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(decoded, image);
+    }
+}
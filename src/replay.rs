@@ -0,0 +1,218 @@
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+#[cfg(feature = "scene")]
+use std::fs;
+use std::time::Instant;
+
+use piston::input::{ButtonArgs, Motion, UpdateArgs};
+
+/// The inputs `App` reacts to, in the order they arrived
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
+pub enum InputEvent {
+    Button(ButtonArgs),
+    Motion(Motion),
+    Update(UpdateArgs),
+}
+
+/// An input along with the seconds since recording started
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
+pub struct RecordedEvent {
+    pub time: f64,
+    pub event: InputEvent,
+}
+
+/// Collects inputs so they can be written to a file and replayed later
+pub struct Recorder {
+    start: Instant,
+    events: Vec<RecordedEvent>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder {
+            start: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: InputEvent) {
+        self.events.push(RecordedEvent {
+            time: self.start.elapsed().as_secs_f64(),
+            event,
+        });
+    }
+
+    /// Write everything recorded so far to a json file
+    #[cfg(feature = "scene")]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = serde_json::to_string(&self.events)
+            .map_err(|e| format!("Could not serialize {}: {}", path, e))?;
+        fs::write(path, text).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    #[cfg(not(feature = "scene"))]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        Err(format!(
+            "Could not write {}: recording needs the scene feature",
+            path
+        ))
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder::new()
+    }
+}
+
+/// Recorded inputs waiting to be fed back in
+pub struct Replay {
+    events: VecDeque<RecordedEvent>,
+}
+
+impl Replay {
+    #[cfg_attr(not(feature = "scene"), allow(dead_code))]
+    pub fn new(events: Vec<RecordedEvent>) -> Replay {
+        Replay {
+            events: events.into(),
+        }
+    }
+
+    /// Read a file written by `Recorder::save`
+    #[cfg(feature = "scene")]
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let events =
+            serde_json::from_str(&text).map_err(|e| format!("Could not parse {}: {}", path, e))?;
+        Ok(Replay::new(events))
+    }
+
+    #[cfg(not(feature = "scene"))]
+    pub fn load(path: &str) -> Result<Replay, String> {
+        Err(format!(
+            "Could not read {}: replays need the scene feature",
+            path
+        ))
+    }
+
+    /// The inputs up to and including the next update, so each live update
+    /// plays back one recorded one. Empty once the recording runs out.
+    pub fn next_frame(&mut self) -> Vec<InputEvent> {
+        let mut frame = Vec::new();
+        while let Some(recorded) = self.events.pop_front() {
+            frame.push(recorded.event);
+            if let InputEvent::Update(_) = recorded.event {
+                break;
+            }
+        }
+        frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston::input::{Button, ButtonState, Key, MouseButton};
+
+    fn button(button: Button, state: ButtonState) -> InputEvent {
+        InputEvent::Button(ButtonArgs {
+            state,
+            button,
+            scancode: None,
+        })
+    }
+
+    fn update(dt: f64) -> InputEvent {
+        InputEvent::Update(UpdateArgs { dt })
+    }
+
+    /// Fly forward while pitching, then orbit with the mouse
+    fn flight() -> Vec<InputEvent> {
+        let mut events = vec![
+            button(Button::Keyboard(Key::Space), ButtonState::Press),
+            button(Button::Keyboard(Key::W), ButtonState::Press),
+        ];
+        events.extend((0..20).map(|_| update(1.0 / 60.0)));
+        events.push(button(Button::Keyboard(Key::W), ButtonState::Release));
+        events.push(button(
+            Button::Mouse(MouseButton::Right),
+            ButtonState::Press,
+        ));
+        for i in 0..20 {
+            events.push(InputEvent::Motion(Motion::MouseRelative([
+                3.0,
+                i as f64 - 10.0,
+            ])));
+            events.push(update(1.0 / 60.0));
+        }
+        events
+    }
+
+    fn recorded(events: &[InputEvent]) -> Vec<RecordedEvent> {
+        let mut recorder = Recorder::new();
+        for &event in events {
+            recorder.record(event);
+        }
+        recorder.events
+    }
+
+    #[test]
+    fn frames_end_after_each_update() {
+        let mouse = InputEvent::Motion(Motion::MouseCursor([1.0, 2.0]));
+        let press = button(Button::Keyboard(Key::W), ButtonState::Press);
+        let mut replay = Replay::new(recorded(&[mouse, press, update(0.1), update(0.2), mouse]));
+
+        assert_eq!(replay.next_frame(), vec![mouse, press, update(0.1)]);
+        assert_eq!(replay.next_frame(), vec![update(0.2)]);
+        assert!(!replay.is_finished());
+        // anything after the last update still gets played
+        assert_eq!(replay.next_frame(), vec![mouse]);
+        assert!(replay.is_finished());
+        assert_eq!(replay.next_frame(), vec![]);
+    }
+
+    #[cfg(feature = "scene")]
+    #[test]
+    fn recordings_round_trip_through_files() {
+        let mut recorder = Recorder::new();
+        for event in flight() {
+            recorder.record(event);
+        }
+        let path = std::env::temp_dir().join("fieldgame-recording.json");
+        let path = path.to_string_lossy();
+        recorder.save(&path).unwrap();
+
+        let loaded = Replay::load(&path).unwrap();
+        assert_eq!(Vec::from(loaded.events), recorder.events);
+    }
+
+    #[test]
+    fn replaying_a_recording_ends_in_the_same_place() {
+        let mut live = crate::initial_app(720.0, 1.0, 40.0, 0.0, "octahedron");
+        live.recorder = Some(Recorder::new());
+        for event in flight() {
+            live.input(event);
+        }
+        let events = live.recorder.take().unwrap().events;
+
+        let mut replayed = crate::initial_app(720.0, 1.0, 40.0, 0.0, "octahedron");
+        let mut replay = Replay::new(events);
+        while !replay.is_finished() {
+            for event in replay.next_frame() {
+                replayed.input(event);
+            }
+        }
+
+        assert_ne!(live.camera, live.initial_camera);
+        assert_eq!(replayed.camera, live.camera);
+        assert_eq!(replayed.velocity, live.velocity);
+    }
+}
@@ -0,0 +1,61 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming an extra directory to look for assets in
+pub const ASSETS_VAR: &str = "FIELDGAME_ASSETS";
+
+/// Find an asset file given its path relative to the repository root.
+///
+/// This looks in the `FIELDGAME_ASSETS` directory if it is set, then the
+/// working directory, then the executable's directory and each of its parents,
+/// so the game can be started from anywhere. If the file isn't found in any of
+/// these the name is returned as is, so the error mentions the original path.
+pub fn resource_path(name: &str) -> PathBuf {
+    let mut roots = Vec::new();
+    if let Some(dir) = env::var_os(ASSETS_VAR) {
+        roots.push(PathBuf::from(dir));
+    }
+    roots.push(PathBuf::from("."));
+    if let Ok(exe) = env::current_exe() {
+        roots.extend(exe.ancestors().skip(1).map(Path::to_path_buf));
+    }
+
+    roots
+        .into_iter()
+        .map(|root| root.join(name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+
+    /// Tests that set `ASSETS_VAR` hold this, since the environment is shared
+    /// by every test thread
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn assets_are_found_in_the_assets_directory() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let dir = env::temp_dir().join("fieldgame-assets");
+        fs::create_dir_all(dir.join("models")).unwrap();
+        let asset = dir.join("models").join("fieldgame-asset.obj");
+        fs::write(&asset, "v 0 0 0\n").unwrap();
+
+        env::set_var(ASSETS_VAR, &dir);
+        let found = resource_path("models/fieldgame-asset.obj");
+        env::remove_var(ASSETS_VAR);
+
+        assert_eq!(found, asset);
+    }
+
+    #[test]
+    fn missing_assets_keep_their_name() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let name = "models/fieldgame-nowhere.obj";
+        assert_eq!(resource_path(name), PathBuf::from(name));
+    }
+}
@@ -0,0 +1,310 @@
+#[cfg(feature = "scene")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "scene")]
+use std::fs;
+use std::sync::mpsc;
+
+use super::mesh;
+use super::r3::{pose::Pose, R3};
+use super::render::Camera;
+use super::{GameObject, Integrator};
+
+/// Everything stored in a scene file
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
+pub struct Scene {
+    /// Where the camera starts, if not the default spot
+    #[cfg_attr(feature = "scene", serde(default))]
+    pub camera: Option<Camera>,
+    pub objects: Vec<SceneObject>,
+}
+
+/// The description of a single object in a scene file
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "scene", derive(Serialize, Deserialize))]
+pub struct SceneObject {
+    /// A path to an obj or stl file, or a primitive, along with its color and scale
+    #[cfg_attr(feature = "scene", serde(flatten))]
+    pub mesh: mesh::MeshSpec,
+    pub pose: Pose,
+
+    pub velocity: R3,
+    pub angular_velocity: R3,
+}
+
+/// Read a json scene file
+#[cfg(feature = "scene")]
+pub fn read_scene(path: &str) -> Result<Scene, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Could not parse {}: {}", path, e))
+}
+
+#[cfg(not(feature = "scene"))]
+pub fn read_scene(path: &str) -> Result<Scene, String> {
+    Err(format!(
+        "Could not read {}: scenes need the scene feature",
+        path
+    ))
+}
+
+/// Write a scene to a json file, in the format `read_scene` expects
+#[cfg(feature = "scene")]
+pub fn write_scene(path: &str, scene: &Scene) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(scene)
+        .map_err(|e| format!("Could not serialize {}: {}", path, e))?;
+    fs::write(path, text).map_err(|e| format!("Could not write {}: {}", path, e))
+}
+
+#[cfg(not(feature = "scene"))]
+pub fn write_scene(path: &str, _scene: &Scene) -> Result<(), String> {
+    Err(format!(
+        "Could not write {}: scenes need the scene feature",
+        path
+    ))
+}
+
+/// The scene as it is right now, for `write_scene`. Objects that don't know
+/// where their mesh came from are left out.
+pub fn capture(camera: Camera, objects: &[GameObject]) -> Scene {
+    Scene {
+        camera: Some(camera),
+        objects: objects
+            .iter()
+            .filter_map(|obj| {
+                Some(SceneObject {
+                    mesh: obj.spec.clone()?,
+                    pose: obj.pose,
+                    velocity: obj.velocity,
+                    angular_velocity: obj.angular_velocity,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// A scene whose meshes are still loading on a background thread, see `load_scene`
+pub struct SceneLoad {
+    /// Where the camera starts, if the scene gives one
+    pub camera: Option<Camera>,
+    objects: Vec<SceneObject>,
+    meshes: Vec<Option<mesh::Mesh>>,
+    loaded: usize,
+    receiver: mpsc::Receiver<Result<(usize, mesh::Mesh), mesh::MeshError>>,
+}
+
+impl SceneLoad {
+    /// How many meshes have loaded so far, and how many there are in total
+    pub fn progress(&self) -> (usize, usize) {
+        (self.loaded, self.objects.len())
+    }
+
+    /// Take any meshes that finished since last time, without waiting for the
+    /// rest. Gives the scene's objects once every mesh is in, or the first
+    /// mesh that failed to load.
+    pub fn poll(&mut self) -> Result<Option<Vec<GameObject>>, String> {
+        while self.loaded < self.objects.len() {
+            match self.receiver.try_recv() {
+                Ok(result) => {
+                    let (i, mesh) = result.map_err(|e| e.to_string())?;
+                    self.meshes[i] = Some(mesh);
+                    self.loaded += 1;
+                }
+                Err(mpsc::TryRecvError::Empty) => return Ok(None),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Err(format!(
+                        "Loading stopped after {} of {} meshes",
+                        self.loaded,
+                        self.objects.len()
+                    ))
+                }
+            }
+        }
+
+        let meshes = self.meshes.iter_mut().map(Option::take);
+        Ok(Some(
+            self.objects
+                .iter()
+                .zip(meshes)
+                .map(|(object, mesh)| {
+                    let mesh = mesh.expect("every mesh has loaded");
+
+                    GameObject {
+                        bounding_radius: mesh.bounding_radius(),
+                        bounding_sphere: mesh.bounding_sphere(),
+                        mesh,
+                        spec: Some(object.mesh.clone()),
+                        pose: object.pose,
+                        world_vertices: None,
+                        visible: true,
+                        physics_enabled: true,
+                        integrator: Integrator::Euler,
+
+                        acceleration: R3::zero(),
+                        velocity: object.velocity,
+
+                        angular_acceleration: R3::zero(),
+                        angular_velocity: object.angular_velocity,
+                    }
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Read a json scene file and start loading all the meshes it refers to in
+/// the background. Poll the result to find out when they are ready.
+pub fn load_scene(path: &str) -> Result<SceneLoad, String> {
+    let Scene { camera, objects } = read_scene(path)?;
+    let receiver =
+        mesh::load_meshes_async(objects.iter().map(|object| object.mesh.clone()).collect());
+
+    Ok(SceneLoad {
+        camera,
+        meshes: objects.iter().map(|_| None).collect(),
+        objects,
+        loaded: 0,
+        receiver,
+    })
+}
+
+#[cfg(all(test, feature = "scene"))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Poll a load until it finishes, failing the test if that takes too long
+    fn finish(mut loading: SceneLoad) -> Result<Vec<GameObject>, String> {
+        let start = Instant::now();
+        loop {
+            if let Some(objects) = loading.poll()? {
+                return Ok(objects);
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "loading never finished"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn reads_the_collision_scene() {
+        let scene = read_scene("scenes/collision.json").unwrap();
+        assert_eq!(scene.camera, None);
+        assert_eq!(scene.objects.len(), 2);
+        assert_eq!(
+            scene.objects[0].mesh.source,
+            mesh::MeshSource::File("data/octahedron.obj".to_string())
+        );
+        assert_eq!(scene.objects[0].mesh.scale, 0.25);
+        assert_eq!(scene.objects[1].pose.pos, R3::new(0.0, 40.0, 10.0));
+        assert_eq!(scene.objects[1].velocity, R3::new(0.0, -10.0, 0.0));
+    }
+
+    #[test]
+    fn loads_the_collision_scene_in_the_background() {
+        let loading = load_scene("scenes/collision.json").unwrap();
+        assert_eq!(loading.progress().1, 2);
+
+        let objects = finish(loading).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].velocity, R3::new(0.0, 10.0, 0.0));
+        assert!(!objects[0].mesh.vertices.is_empty());
+    }
+
+    #[test]
+    fn reports_meshes_that_fail_to_load() {
+        let path = std::env::temp_dir().join("fieldgame-missing-mesh-scene.json");
+        let scene = Scene {
+            camera: None,
+            objects: vec![SceneObject {
+                mesh: mesh::MeshSpec {
+                    source: mesh::MeshSource::File("data/does-not-exist.obj".to_string()),
+                    color: [1.0; 4],
+                    scale: 1.0,
+                },
+                pose: Pose {
+                    pos: R3::zero(),
+                    orientation: crate::Quaternion::zero_rotation(),
+                },
+                velocity: R3::zero(),
+                angular_velocity: R3::zero(),
+            }],
+        };
+        let path = path.to_string_lossy();
+        write_scene(&path, &scene).unwrap();
+
+        let error = finish(load_scene(&path).unwrap()).map(|_| ()).unwrap_err();
+        assert!(error.contains("does-not-exist.obj"), "{}", error);
+    }
+
+    fn two_object_scene() -> Scene {
+        let still = |pos| Pose {
+            pos,
+            orientation: crate::Quaternion::rotation(-R3::UP, 0.5),
+        };
+        Scene {
+            camera: Some(Camera {
+                position: R3::new(-30.0, 0.0, -30.0),
+                orientation: crate::Quaternion::look_at(R3::new(30.0, 0.0, 30.0), -R3::UP),
+                scale: 100.0,
+                projection: crate::render::ProjectionMode::Perspective { fov: 1.5 },
+            }),
+            objects: vec![
+                SceneObject {
+                    mesh: mesh::MeshSpec {
+                        source: mesh::MeshSource::File("data/octahedron.obj".to_string()),
+                        color: [0.0, 0.25, 0.5, 1.0],
+                        scale: 0.25,
+                    },
+                    pose: still(R3::new(0.0, -40.0, 0.0)),
+                    velocity: R3::new(0.0, 10.0, 0.0),
+                    angular_velocity: R3::new(0.0, 0.0, -1.0),
+                },
+                SceneObject {
+                    mesh: mesh::MeshSpec {
+                        source: mesh::MeshSource::Primitive(mesh::Primitive::UvSphere {
+                            radius: 10.0,
+                            rings: 6,
+                            segments: 12,
+                        }),
+                        color: [0.5, 0.25, 0.0, 1.0],
+                        scale: 1.0,
+                    },
+                    pose: still(R3::new(0.0, 40.0, 10.0)),
+                    velocity: R3::new(0.0, -10.0, 0.0),
+                    angular_velocity: R3::zero(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn scenes_round_trip_through_files() {
+        let scene = two_object_scene();
+        let path = std::env::temp_dir().join("fieldgame-round-trip-scene.json");
+        let path = path.to_string_lossy();
+
+        write_scene(&path, &scene).unwrap();
+        assert_eq!(read_scene(&path).unwrap(), scene);
+    }
+
+    #[test]
+    fn meshes_are_paths_or_named_primitives() {
+        let json = serde_json::to_value(two_object_scene()).unwrap();
+        assert_eq!(json["objects"][0]["mesh"], "data/octahedron.obj");
+        assert_eq!(json["objects"][1]["mesh"]["primitive"], "uv_sphere");
+        assert_eq!(json["objects"][1]["mesh"]["rings"], 6);
+    }
+
+    #[test]
+    fn capturing_a_loaded_scene_gives_it_back() {
+        let scene = read_scene("scenes/collision.json").unwrap();
+        let objects = finish(load_scene("scenes/collision.json").unwrap()).unwrap();
+
+        let camera = two_object_scene().camera.unwrap();
+        let captured = capture(camera, &objects);
+        assert_eq!(captured.camera, Some(camera));
+        assert_eq!(captured.objects, scene.objects);
+    }
+}